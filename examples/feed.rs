@@ -1,23 +1,21 @@
 use std::time::{Instant, Duration};
 
 use anyhow::{Context as _, Result as AnyResult};
-use vjoy::{Interface};
+use vjoy::{Interface, RateLimiter};
 
 fn main() -> AnyResult<()> {
     let vjoy = Interface::new()?;
 
-	let device = vjoy.device_slots()?
-		.filter_map(|d| d.acquire().ok())
-		.next()
+	let device = vjoy.acquire_any()
 		.context("Failed to acquire a vJoy device!")?;
 
 	println!("Acquired vJoy device #{}.", device.id());
 	let num_buttons = device.num_buttons()?;
+	let num_cont_pov = device.num_cont_pov()?;
 
 	let start = Instant::now();
 
-	let mut next = start;
-	let period = Duration::from_nanos(1_000_000_000 / 125);
+	let mut limiter = RateLimiter::new(125).unwrap();
 
 	let mut time_samples = 0;
 	let mut time_total = Duration::ZERO;
@@ -42,6 +40,11 @@ fn main() -> AnyResult<()> {
 			device.set_button(button, phase < 0.5)?;
 		}
 
+		for pov in 0..num_cont_pov {
+			let angle = f64::fract(t / (4.0 + pov as f64)) * 36000.0;
+			device.set_cont_pov(pov, Some((angle as u16).min(vjoy::MAX_POV_CENTIDEGREES)))?;
+		}
+
 		time_total += before.elapsed();
 		time_samples += 1;
 
@@ -50,12 +53,7 @@ fn main() -> AnyResult<()> {
 			println!("Average update time over {} samples: {:#?}", time_samples, time_avg);
 		}
 
+		limiter.wait();
 		device.apply()?;
-		next += period;
-
-		let now = Instant::now();
-		if let Some(pause) = next.checked_duration_since(now) {
-			std::thread::sleep(pause);
-		}
 	}
 }
@@ -0,0 +1,150 @@
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+
+use crate::{Axis, DeviceId, Error, Interface, OwnedDeviceSlot};
+
+type Command = Box<dyn FnOnce(&OwnedDeviceSlot) + Send>;
+
+/// A `VJoyActor` owns a vJoy device on a dedicated thread, allowing it to be controlled from any
+/// thread.
+///
+/// All vJoy access is confined to a single thread (see `Interface::new`), so an `OwnedDeviceSlot`
+/// cannot be shared across threads. Instead, the actor's thread creates the `Interface` and
+/// acquires the device, and each method sends a command to it and waits for the result. Because of
+/// this, no other `Interface` may exist while the actor is running.
+///
+/// Dropping the actor stops its thread, which relinquishes the device.
+#[derive(Debug)]
+pub struct VJoyActor {
+	commands: Option<Sender<Command>>,
+	thread: Option<JoinHandle<()>>,
+}
+
+// The actor's purpose is to make the device usable from other threads.
+static_assertions::assert_impl_all!(VJoyActor: Send, Sync);
+
+impl VJoyActor {
+	/// Spawns the actor's thread, which acquires the device with the given ID.
+	pub fn spawn(id: DeviceId) -> Result<VJoyActor, Error> {
+		let (commands, receiver) = mpsc::channel::<Command>();
+		let (started, start) = mpsc::sync_channel(0);
+
+		let thread = thread::Builder::new()
+			.name(format!("vjoy-{}", id))
+			.spawn(move || {
+				let device = match acquire(id) {
+					Ok(device) => device,
+					Err(e) => {
+						let _ = started.send(Err(e));
+						return;
+					},
+				};
+
+				let _ = started.send(Ok(()));
+				for command in receiver {
+					command(&device);
+				}
+			})
+			.map_err(|_| ActorError::Spawn)?;
+
+		start.recv()
+			.unwrap_or(Err(ActorError::Stopped.into()))?;
+
+		Ok(VJoyActor {
+			commands: Some(commands),
+			thread: Some(thread),
+		})
+	}
+
+	/// Runs a function with the device on the actor's thread, and returns its result.
+	pub fn run<R: Send + 'static>(&self, f: impl FnOnce(&OwnedDeviceSlot) -> R + Send + 'static) -> Result<R, ActorError> {
+		let (result, receiver) = mpsc::sync_channel(1);
+		let command: Command = Box::new(move |device| {
+			let _ = result.send(f(device));
+		});
+
+		self.commands.as_ref()
+			.and_then(|c| c.send(command).ok())
+			.ok_or(ActorError::Stopped)?;
+
+		receiver.recv()
+			.map_err(|_| ActorError::Stopped)
+	}
+
+	/// Applies the device's buffered state on the actor's thread, blocking until it has been applied.
+	/// Fails with `ActorError::Stopped` if the actor's thread has stopped.
+	pub fn apply(&self) -> Result<(), Error> {
+		Ok(self.run(|d| d.apply())??)
+	}
+
+	/// Gets the ID of the actor's device, blocking on a round-trip to the actor's thread. Fails with
+	/// `ActorError::Stopped` if the actor's thread has stopped.
+	pub fn id(&self) -> Result<DeviceId, ActorError> {
+		self.run(|d| d.id())
+	}
+
+	/// Resets the device and its buffered state on the actor's thread, blocking until it has been
+	/// reset. Fails with `ActorError::Stopped` if the actor's thread has stopped.
+	pub fn reset(&self) -> Result<(), Error> {
+		Ok(self.run(|d| d.reset())??)
+	}
+
+	/// Sets an axis of the buffered state to a normalized value (`0.0..=1.0`) on the actor's thread,
+	/// blocking until it has been set. Fails with `ActorError::Stopped` if the actor's thread has
+	/// stopped.
+	pub fn set_axis_f32(&self, axis: Axis, value: f32) -> Result<(), Error> {
+		Ok(self.run(move |d| d.set_axis_f32(axis, value))??)
+	}
+
+	/// Sets an axis of the buffered state to a raw value on the actor's thread, blocking until it has
+	/// been set. Fails with `ActorError::Stopped` if the actor's thread has stopped.
+	pub fn set_axis_raw(&self, axis: Axis, value: i32) -> Result<(), Error> {
+		Ok(self.run(move |d| d.set_axis_raw(axis, value))??)
+	}
+
+	/// Sets a button of the buffered state on the actor's thread, blocking until it has been set.
+	/// Fails with `ActorError::Stopped` if the actor's thread has stopped.
+	pub fn set_button(&self, index: usize, value: bool) -> Result<(), Error> {
+		Ok(self.run(move |d| d.set_button(index, value))??)
+	}
+
+	/// Sets a continuous POV of the buffered state in centidegrees (or centers it if `None` is given)
+	/// on the actor's thread, blocking until it has been set. Fails with `ActorError::Stopped` if the
+	/// actor's thread has stopped.
+	pub fn set_cont_pov(&self, index: usize, value: Option<u16>) -> Result<(), Error> {
+		Ok(self.run(move |d| d.set_cont_pov(index, value))??)
+	}
+}
+
+impl Drop for VJoyActor {
+	fn drop(&mut self) {
+		// Closing the channel ends the thread's command loop.
+		drop(self.commands.take());
+
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+fn acquire(id: DeviceId) -> Result<OwnedDeviceSlot, Error> {
+	let vjoy = Interface::new()?;
+	let slot = vjoy.device_slot(id)?
+		.ok_or(ActorError::NoSuchSlot)?;
+
+	Ok(slot.try_acquire().map_err(|(_, e)| e)?)
+}
+
+/// An error from a `VJoyActor`. `Stopped` is returned by any call made after the actor's thread has
+/// stopped, e.g. because a command run on it panicked.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
+pub enum ActorError {
+	#[error("The vJoy device slot does not exist.")]
+	NoSuchSlot,
+
+	#[error("Failed to spawn the vJoy actor thread.")]
+	Spawn,
+
+	#[error("The vJoy actor thread has stopped.")]
+	Stopped,
+}
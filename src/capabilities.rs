@@ -0,0 +1,233 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display};
+use std::ops::{RangeInclusive};
+
+use crate::{Axis, AxisRangeError, DeviceSlot, FfbEffect, NumButtonsError, NumContPovError, NumDiscPovError, util};
+
+/// `Capabilities` describes the configuration of a vJoy device: its number of buttons and POVs, as
+/// well as which axes it has and their ranges.
+///
+/// `Capabilities` may be compared and hashed (e.g. for use as a key in a `HashMap`). Axes are always
+/// stored in a consistent order, so equal configurations will always compare and hash equally.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Capabilities {
+	pub(crate) num_buttons: usize,
+	pub(crate) num_cont_pov: usize,
+	pub(crate) num_disc_pov: usize,
+	pub(crate) axes: BTreeMap<Axis, RangeInclusive<i32>>,
+}
+
+/// The device capability snapshot returned by `DeviceSlot::capabilities`.
+pub type DeviceCapabilities = Capabilities;
+
+impl Capabilities {
+	pub fn axes(&self) -> impl Iterator<Item = Axis> + '_ {
+		self.axes.keys().copied()
+	}
+
+	pub fn axis_range(&self, axis: Axis) -> Option<RangeInclusive<i32>> {
+		self.axes.get(&axis).cloned()
+	}
+
+	pub fn axis_ranges(&self) -> &BTreeMap<Axis, RangeInclusive<i32>> {
+		&self.axes
+	}
+
+	/// Computes a compact fingerprint of this device configuration.
+	///
+	/// The fingerprint is computed with a fixed hash function, so it is stable across runs and may be
+	/// persisted (e.g. to associate a saved profile with a device configuration).
+	pub fn fingerprint(&self) -> u64 {
+		let mut hasher = util::Fnv1a::new();
+		hasher.write(&(self.num_buttons as u64).to_le_bytes());
+		hasher.write(&(self.num_cont_pov as u64).to_le_bytes());
+		hasher.write(&(self.num_disc_pov as u64).to_le_bytes());
+
+		for (axis, range) in &self.axes {
+			hasher.write(&axis.usage().to_le_bytes());
+			hasher.write(&range.start().to_le_bytes());
+			hasher.write(&range.end().to_le_bytes());
+		}
+
+		hasher.finish()
+	}
+
+	pub fn has_axis(&self, axis: Axis) -> bool {
+		self.axes.contains_key(&axis)
+	}
+
+	pub fn num_buttons(&self) -> usize {
+		self.num_buttons
+	}
+
+	pub fn num_cont_pov(&self) -> usize {
+		self.num_cont_pov
+	}
+
+	pub fn num_disc_pov(&self) -> usize {
+		self.num_disc_pov
+	}
+}
+
+impl Display for Capabilities {
+	/// Formats the capabilities over several lines, in the same format used by the `info` example.
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		writeln!(f, "  Buttons: {}", self.num_buttons)?;
+		write!(f, "  POVs: {} discrete, {} continuous", self.num_disc_pov, self.num_cont_pov)?;
+
+		for (axis, range) in &self.axes {
+			write!(f, "\n  Axis #{} ({}): {} to {}", *axis as u8, axis.name(), range.start(), range.end())?;
+		}
+
+		Ok(())
+	}
+}
+
+/// `Requirements` describes the minimum capabilities that a vJoy device must have in order to be
+/// suitable for some purpose, e.g. for use with `Interface::find_device`.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Requirements {
+	axes: BTreeSet<Axis>,
+	ffb_effects: BTreeSet<FfbEffect>,
+	num_buttons: usize,
+	num_cont_pov: usize,
+	num_disc_pov: usize,
+}
+
+impl Requirements {
+	pub fn new() -> Requirements {
+		Requirements::default()
+	}
+
+	pub fn axis(mut self, axis: Axis) -> Requirements {
+		self.axes.insert(axis);
+		self
+	}
+
+	pub fn axes(mut self, axes: impl IntoIterator<Item = Axis>) -> Requirements {
+		self.axes.extend(axes);
+		self
+	}
+
+	pub fn ffb_effect(mut self, effect: FfbEffect) -> Requirements {
+		self.ffb_effects.insert(effect);
+		self
+	}
+
+	pub fn ffb_effects(mut self, effects: impl IntoIterator<Item = FfbEffect>) -> Requirements {
+		self.ffb_effects.extend(effects);
+		self
+	}
+
+	pub fn buttons(mut self, num: usize) -> Requirements {
+		self.num_buttons = num;
+		self
+	}
+
+	pub fn cont_povs(mut self, num: usize) -> Requirements {
+		self.num_cont_pov = num;
+		self
+	}
+
+	pub fn disc_povs(mut self, num: usize) -> Requirements {
+		self.num_disc_pov = num;
+		self
+	}
+
+	/// Checks whether the given capabilities satisfy these requirements.
+	///
+	/// Force-feedback support is not part of `Capabilities`, so any required force-feedback effects
+	/// are not checked by this method. Use `matches` to check them as well.
+	pub fn is_satisfied_by(&self, caps: &Capabilities) -> bool {
+		caps.num_buttons >= self.num_buttons
+			&& caps.num_cont_pov >= self.num_cont_pov
+			&& caps.num_disc_pov >= self.num_disc_pov
+			&& self.axes.iter().all(|a| caps.has_axis(*a))
+	}
+
+	/// Checks whether a device satisfies these requirements, including any required force-feedback
+	/// effects. A device whose capabilities cannot be retrieved is never considered to satisfy them.
+	pub fn matches(&self, device: &DeviceSlot) -> bool {
+		let ffb = self.ffb_effects.is_empty()
+			|| (device.is_ffb() && self.ffb_effects.iter().all(|e| device.supports_ffb_effect(*e)));
+
+		ffb && device.capabilities()
+			.is_ok_and(|c| self.is_satisfied_by(&c))
+	}
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
+pub enum CapabilitiesError {
+	#[error(transparent)]
+	AxisRange(#[from] AxisRangeError),
+
+	#[error(transparent)]
+	NumButtons(#[from] NumButtonsError),
+
+	#[error(transparent)]
+	NumContPov(#[from] NumContPovError),
+
+	#[error(transparent)]
+	NumDiscPov(#[from] NumDiscPovError),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[cfg(feature = "mock")]
+	use crate::mock::{MockDevice, TestInterface, test_device};
+
+	fn caps(num_buttons: usize, axes: &[(Axis, RangeInclusive<i32>)]) -> Capabilities {
+		Capabilities {
+			num_buttons,
+			num_cont_pov: 1,
+			num_disc_pov: 0,
+			axes: axes.iter().cloned().collect(),
+		}
+	}
+
+	#[test]
+	fn fingerprint_is_stable() {
+		let caps = caps(8, &[(Axis::X, 0..=32767), (Axis::Y, 0..=32767)]);
+		assert_eq!(caps.fingerprint(), 12801955560465032201);
+	}
+
+	#[test]
+	fn fingerprint_changes_with_configuration() {
+		let base = caps(8, &[(Axis::X, 0..=32767), (Axis::Y, 0..=32767)]);
+
+		let variants = [
+			caps(9, &[(Axis::X, 0..=32767), (Axis::Y, 0..=32767)]),
+			caps(8, &[(Axis::X, 0..=32767)]),
+			caps(8, &[(Axis::X, 0..=32767), (Axis::Z, 0..=32767)]),
+			caps(8, &[(Axis::X, 0..=32767), (Axis::Y, 0..=16383)]),
+			Capabilities { num_disc_pov: 1, ..base.clone() },
+		];
+
+		for variant in &variants {
+			assert_ne!(variant.fingerprint(), base.fingerprint(), "{:?}", variant);
+		}
+
+		assert_eq!(base.clone().fingerprint(), base.fingerprint());
+	}
+
+	#[cfg(feature = "mock")]
+	#[test]
+	fn unsupported_ffb_effect_matches_nothing() {
+		let vjoy = TestInterface::new([
+			test_device(),
+			MockDevice { ffb: true, ffb_effects: [FfbEffect::Constant, FfbEffect::Sine].into(), ..test_device() },
+		]);
+
+		let requirements = Requirements::new().ffb_effect(FfbEffect::Spring);
+		assert!(vjoy.present_devices().unwrap().all(|d| !requirements.matches(&d)));
+
+		let requirements = Requirements::new().ffb_effect(FfbEffect::Constant);
+		let matching: Vec<_> = vjoy.present_devices().unwrap()
+			.filter(|d| requirements.matches(d))
+			.map(|d| d.id().to_raw())
+			.collect();
+		assert_eq!(matching, [2]);
+	}
+}
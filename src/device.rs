@@ -1,10 +1,21 @@
-use std::cell::RefCell;
+use std::cell::{Cell};
+use std::collections::{BTreeMap};
 use std::fmt::{Display};
-use std::num::{NonZeroU8};
+use std::num::{IntErrorKind, NonZeroU8};
 use std::ops::{Deref, RangeInclusive};
-
+use std::str::{FromStr};
+use std::sync::{Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::capabilities::{Capabilities, CapabilitiesError};
+use crate::ffb::{FfbEffect};
+use crate::ffi;
 use crate::interface::{Interface};
 use crate::lock::{VJoyLock};
+use crate::rate::{RateLimiter};
+use crate::state::{DeviceState, DirtyFlags};
+use crate::timeline::{Timeline};
 
 /// A `DeviceId` is a numeric ID representing which slot a vJoy device is in.
 ///
@@ -12,6 +23,8 @@ use crate::lock::{VJoyLock};
 /// 16. In this library, it is allowed to be as large as 255, but `Interface` will not create
 /// `DeviceSlot` instances for device IDs larger than the driver supports.
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 #[repr(transparent)]
 pub struct DeviceId(NonZeroU8);
 type RawDeviceId = u8;
@@ -33,7 +46,7 @@ impl DeviceId {
 	}
 
 	pub fn to_index(self) -> usize {
-		self.to_raw() as usize + 1
+		self.to_raw() as usize - 1
 	}
 
 	pub fn to_raw(self) -> RawDeviceId {
@@ -71,6 +84,21 @@ impl From<NonZeroU8> for DeviceId {
 	}
 }
 
+impl FromStr for DeviceId {
+	type Err = ParseDeviceIdError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let raw = s.parse::<RawDeviceId>()
+			.map_err(|e| match e.kind() {
+				IntErrorKind::PosOverflow => ParseDeviceIdError::TooLarge,
+				_ => ParseDeviceIdError::Invalid,
+			})?;
+
+		DeviceId::from_raw(raw)
+			.map_err(|_| ParseDeviceIdError::Zero)
+	}
+}
+
 impl TryFrom<RawDeviceId> for DeviceId {
 	type Error = DeviceIdFromRawError;
 
@@ -91,22 +119,72 @@ impl TryFrom<usize> for DeviceId {
 pub struct DeviceSlot {
 	id: DeviceId,
 	lock: VJoyLock,
+	standard_range: bool,
 }
 
 impl DeviceSlot {
-	pub(crate) fn new(id: DeviceId, lock: VJoyLock) -> DeviceSlot {
-		DeviceSlot { id, lock }
+	pub(crate) fn new(id: DeviceId, lock: VJoyLock, standard_range: bool) -> DeviceSlot {
+		DeviceSlot { id, lock, standard_range }
 	}
 
+	/// Acquires the device so that its inputs may be fed. Its axes start at raw 0, not centered.
 	pub fn acquire(self) -> Result<OwnedDeviceSlot, DeviceSlot> {
-		let acquired = unsafe { vjoy_sys::AcquireVJD(self.id.to_raw() as u32) } != 0;
+		let acquired = unsafe { ffi::AcquireVJD(self.id.to_raw() as u32) } != 0;
 		if acquired {
+			#[cfg(all(feature = "const-range", feature = "tracing", debug_assertions))]
+			self.check_const_ranges();
+
 			Ok(OwnedDeviceSlot::new(self))
 		} else {
 			Err(self)
 		}
 	}
 
+	/// Acquires the device like `acquire`, but on failure also returns the reason that the device
+	/// could not be acquired, including its status at the time.
+	pub fn try_acquire(self) -> Result<OwnedDeviceSlot, (DeviceSlot, AcquireError)> {
+		if !self.is_available() {
+			return Err((self, AcquireError::Missing));
+		}
+
+		self.acquire()
+			.map_err(|slot| {
+				let status = slot.status();
+				(slot, AcquireError::Failed(status))
+			})
+	}
+
+	/// Acquires the device like `acquire`, then centers its axes and POVs and applies them. If
+	/// applying fails, the centered state stays buffered for the next `apply`.
+	pub fn acquire_centered(self) -> Result<OwnedDeviceSlot, DeviceSlot> {
+		let owned = self.acquire()?;
+		owned.center_state();
+		owned.apply().ok();
+
+		Ok(owned)
+	}
+
+	/// Retries `acquire` every `poll_interval` until it succeeds or `timeout` elapses.
+	pub fn acquire_timeout(self, timeout: Duration, poll_interval: Duration) -> Result<OwnedDeviceSlot, (DeviceSlot, AcquireError)> {
+		let deadline = Instant::now() + timeout;
+		let mut slot = self;
+
+		loop {
+			let (failed, error) = match slot.try_acquire() {
+				Ok(owned) => return Ok(owned),
+				Err(failure) => failure,
+			};
+
+			let now = Instant::now();
+			if now >= deadline {
+				return Err((failed, error));
+			}
+
+			std::thread::sleep(poll_interval.min(deadline - now));
+			slot = failed;
+		}
+	}
+
 	pub fn axes(&self) -> impl Iterator<Item = Axis> {
 		let device = self.clone();
 
@@ -114,20 +192,45 @@ impl DeviceSlot {
 			.filter(move |a| device.has_axis(*a))
 	}
 
+	/// With `const-range`, this assumes vJoy's default range without querying the driver.
 	#[cfg(feature = "const-range")]
 	pub const fn axis_range(&self, _axis: Axis) -> Result<RangeInclusive<i32>, AxisRangeError> {
-		Ok(0..=vjoy_sys::VJOY_AXIS_MAX_VALUE as i32)
+		Ok(STANDARD_AXIS_RANGE)
 	}
 
+	/// If the `Interface` was built with `assume_standard_range`, this returns vJoy's default range
+	/// of `0..=VJOY_AXIS_MAX_VALUE` without querying the driver.
 	#[cfg(not(feature = "const-range"))]
 	pub fn axis_range(&self, axis: Axis) -> Result<RangeInclusive<i32>, AxisRangeError> {
+		if self.standard_range {
+			return Ok(STANDARD_AXIS_RANGE);
+		}
+
+		self.driver_axis_range(axis)
+	}
+
+	#[cfg(all(feature = "const-range", feature = "tracing", debug_assertions))]
+	fn check_const_ranges(&self) {
+		for axis in self.axes() {
+			let assumed = self.axis_range(axis);
+			let actual = self.driver_axis_range(axis);
+
+			if actual.is_ok() && actual != assumed {
+				tracing::warn!(device = %self.id, axis = axis.name(), ?actual, ?assumed,
+					"axis range differs from the one assumed by the `const-range` feature");
+			}
+		}
+	}
+
+	#[cfg(any(not(feature = "const-range"), all(feature = "tracing", debug_assertions)))]
+	fn driver_axis_range(&self, axis: Axis) -> Result<RangeInclusive<i32>, AxisRangeError> {
 		let mut min = 0;
-		if unsafe { vjoy_sys::GetVJDAxisMin(self.id.to_raw() as u32, axis.usage(), &mut min) } == 0 {
+		if unsafe { ffi::GetVJDAxisMin(self.id.to_raw() as u32, axis.usage(), &mut min) } == 0 {
 			return Err(AxisRangeError::MinFailure);
 		}
 
 		let mut max = 0;
-		if unsafe { vjoy_sys::GetVJDAxisMax(self.id.to_raw() as u32, axis.usage(), &mut max) } == 0 {
+		if unsafe { ffi::GetVJDAxisMax(self.id.to_raw() as u32, axis.usage(), &mut max) } == 0 {
 			return Err(AxisRangeError::MaxFailure);
 		}
 
@@ -136,13 +239,47 @@ impl DeviceSlot {
 			.ok_or(AxisRangeError::Invalid)
 	}
 
+	pub fn axis_ranges(&self) -> Result<BTreeMap<Axis, RangeInclusive<i32>>, AxisRangeError> {
+		self.axes()
+			.map(|a| self.axis_range(a).map(|r| (a, r)))
+			.collect()
+	}
+
+	pub fn capabilities(&self) -> Result<Capabilities, CapabilitiesError> {
+		Ok(Capabilities {
+			num_buttons: self.num_buttons()?,
+			num_cont_pov: self.num_cont_pov()?,
+			num_disc_pov: self.num_disc_pov()?,
+			axes: self.axis_ranges()?,
+		})
+	}
+
+	/// Clamps a raw value into the range of the given axis.
+	pub fn clamp_axis_raw(&self, axis: Axis, value: i32) -> Result<i32, AxisRangeError> {
+		let range = self.axis_range(axis)?;
+		Ok(clamp_axis_value(&range, value as i64))
+	}
+
+	/// Converts a normalized value in `0.0..=1.0` to a raw value for an axis, where 0.0 is the
+	/// minimum of the axis's range and 1.0 is its maximum. This is the inverse of `normalize_axis`.
+	pub fn denormalize_axis(&self, axis: Axis, value: f32) -> Result<i32, SetAxisError> {
+		let range = self.axis_range(axis)?;
+		denormalize_axis_value(range, value)
+	}
+
     pub fn has_axis(&self, axis: Axis) -> bool {
         // TODO: Why does GetVJDAxisExist return true for axes that don't exist?
 		//(unsafe { vjoy_sys::GetVJDAxisExist(self.id.to_raw() as u32, axis.usage()) } != 0)
 
 		// Workaround: Use function for axis min, which does fail for axes that don't exist
         let mut min = 0;
-		(unsafe { vjoy_sys::GetVJDAxisMin(self.id.to_raw() as u32, axis.usage(), &mut min) } != 0)
+		(unsafe { ffi::GetVJDAxisMin(self.id.to_raw() as u32, axis.usage(), &mut min) } != 0)
+	}
+
+	/// Returns whether the device has the button with the given index, according to its configured
+	/// button count.
+	pub fn has_button(&self, index: usize) -> bool {
+		self.num_buttons().is_ok_and(|n| index < n)
 	}
 
 	pub fn id(&self) -> DeviceId {
@@ -154,174 +291,955 @@ impl DeviceSlot {
 	}
 
 	pub fn interface(&self) -> Interface {
-		Interface::from_lock(self.lock.clone())
+		Interface::from_lock(self.lock.clone(), self.standard_range)
+	}
+
+	pub fn is_ffb(&self) -> bool {
+		(unsafe { ffi::IsDeviceFfb(self.id.to_raw() as u32) } != 0)
 	}
 
 	pub fn is_available(&self) -> bool {
-		(unsafe { vjoy_sys::isVJDExists(self.id.to_raw() as u32) } != 0)
+		(unsafe { ffi::isVJDExists(self.id.to_raw() as u32) } != 0)
+	}
+
+	/// Converts a raw axis value to `0.0..=1.0`, from the minimum of its range to the maximum.
+	/// Fails with `AxisRangeError::Degenerate` if the range holds a single value.
+	pub fn normalize_axis(&self, axis: Axis, raw: i32) -> Result<f32, GetAxisError> {
+		let range = self.axis_range(axis)?;
+		normalize_axis_value(range, raw)
+	}
+
+	/// Returns the number of axes that the device has.
+	pub fn num_axes(&self) -> usize {
+		self.axes().count()
 	}
 
 	pub fn num_buttons(&self) -> Result<usize, NumButtonsError> {
-		let raw = unsafe { vjoy_sys::GetVJDButtonNumber(self.id.to_raw() as u32) };
-		raw.try_into().map_err(|_| NumButtonsError::Failed)
+		let raw = unsafe { ffi::GetVJDButtonNumber(self.id.to_raw() as u32) };
+		usize::try_from(raw)
+			.map_err(|_| NumButtonsError::Failed)
+			.and_then(|n| (n <= MAX_BUTTONS).then_some(n)
+				.ok_or(NumButtonsError::Invalid))
 	}
 
 	pub fn num_cont_pov(&self) -> Result<usize, NumContPovError> {
-		let raw = unsafe { vjoy_sys::GetVJDContPovNumber(self.id.to_raw() as u32) };
-		raw.try_into().map_err(|_| NumContPovError::Failed)
+		let raw = unsafe { ffi::GetVJDContPovNumber(self.id.to_raw() as u32) };
+		usize::try_from(raw)
+			.map_err(|_| NumContPovError::Failed)
+			.and_then(|n| (n <= MAX_POVS).then_some(n)
+				.ok_or(NumContPovError::Invalid))
 	}
 
 	pub fn num_disc_pov(&self) -> Result<usize, NumDiscPovError> {
-		let raw = unsafe { vjoy_sys::GetVJDDiscPovNumber(self.id.to_raw() as u32) };
-		raw.try_into().map_err(|_| NumDiscPovError::Failed)
+		let raw = unsafe { ffi::GetVJDDiscPovNumber(self.id.to_raw() as u32) };
+		usize::try_from(raw)
+			.map_err(|_| NumDiscPovError::Failed)
+			.and_then(|n| (n <= MAX_POVS).then_some(n)
+				.ok_or(NumDiscPovError::Invalid))
+	}
+
+	/// Gets the ID of the process which has acquired the device, or `None` if the device is free or
+	/// missing.
+	pub fn owner_pid(&self) -> Result<Option<u32>, OwnerError> {
+		let raw = unsafe { ffi::GetOwnerPid(self.id.to_raw() as u32) };
+		if let Some(pid) = u32::try_from(raw).ok().filter(|&p| p != 0) {
+			return Ok(Some(pid));
+		}
+
+		match self.status() {
+			Status::Free | Status::Missing => Ok(None),
+			_ => Err(OwnerError::Failed(raw)),
+		}
+	}
+
+	/// Checks whether two slots refer to the same vJoy device, comparing only their IDs.
+	pub fn same_device(&self, other: &DeviceSlot) -> bool {
+		self.id == other.id
 	}
 
 	pub fn status(&self) -> Status {
-		let raw = unsafe { vjoy_sys::GetVJDStatus(self.id.to_raw() as u32) };
+		let raw = unsafe { ffi::GetVJDStatus(self.id.to_raw() as u32) };
 		Status::try_from(raw)
 			.expect("vJoy device status received from interface was invalid")
 	}
+
+	pub fn supports_ffb_effect(&self, effect: FfbEffect) -> bool {
+		(unsafe { ffi::IsDeviceFfbEffect(self.id.to_raw() as u32, effect.usage()) } != 0)
+	}
+
+	/// Blocks until the device's status is `Status::Free` or the timeout elapses, polling the status
+	/// periodically. Fails immediately if the device is missing, since it cannot become free.
+	pub fn wait_until_free(&self, timeout: Duration) -> Result<(), WaitError> {
+		const POLL_INTERVAL: Duration = Duration::from_millis(10);
+		let deadline = Instant::now() + timeout;
+
+		loop {
+			match self.status() {
+				Status::Free => return Ok(()),
+				Status::Missing => return Err(WaitError::Missing),
+				_ => {},
+			}
+
+			let now = Instant::now();
+			if now >= deadline {
+				return Err(WaitError::Timeout);
+			}
+
+			std::thread::sleep(POLL_INTERVAL.min(deadline - now));
+		}
+	}
+}
+
+// Scaling to or from a range which contains only a single value would divide by zero.
+fn check_span(range: &RangeInclusive<i32>) -> Result<(), AxisRangeError> {
+	(range.start() != range.end())
+		.then_some(())
+		.ok_or(AxisRangeError::Degenerate)
+}
+
+pub(crate) fn axis_center(range: &RangeInclusive<i32>) -> i32 {
+	let (lo, hi) = (*range.start() as i64, *range.end() as i64);
+	(lo + (hi - lo) / 2) as i32
+}
+
+fn clamp_axis_value(range: &RangeInclusive<i32>, value: i64) -> i32 {
+	value.clamp(*range.start() as i64, *range.end() as i64) as i32
+}
+
+fn denormalize_axis_value(range: RangeInclusive<i32>, value: f32) -> Result<i32, SetAxisError> {
+	if !(0.0..=1.0).contains(&value) {
+		return Err(SetAxisError::Value);
+	}
+
+	check_span(&range)?;
+
+	let (lo, hi) = (*range.start(), *range.end());
+	let span = hi.wrapping_sub(lo) as u32;
+
+	let raw = lo as i64 + f32::round(span as f32 * value) as i64;
+	Ok(clamp_axis_value(&range, raw))
+}
+
+// Signed values are mapped linearly on each side of the range's center, so that 0.0 is always the
+// center even if the range is asymmetric.
+fn axis_value_to_signed(range: RangeInclusive<i32>, raw: i32) -> Result<f32, GetAxisError> {
+	if !range.contains(&raw) {
+		return Err(GetAxisError::Value);
+	}
+
+	check_span(&range)?;
+
+	let center = axis_center(&range) as i64;
+	let (lo, hi) = (*range.start() as i64, *range.end() as i64);
+	let offset = raw as i64 - center;
+	let half = if offset < 0 { center - lo } else { hi - center };
+
+	if half == 0 {
+		return Ok(0.0);
+	}
+
+	Ok((offset as f64 / half as f64) as f32)
 }
 
+fn signed_to_axis_value(range: RangeInclusive<i32>, value: f32) -> Result<i32, SetAxisError> {
+	if !(-1.0..=1.0).contains(&value) {
+		return Err(SetAxisError::Value);
+	}
+
+	check_span(&range)?;
+
+	let center = axis_center(&range) as i64;
+	let (lo, hi) = (*range.start() as i64, *range.end() as i64);
+	let half = if value < 0.0 { center - lo } else { hi - center };
+
+	let raw = center + f64::round(half as f64 * value as f64) as i64;
+	Ok(clamp_axis_value(&range, raw))
+}
+
+// i16 values are mapped linearly onto the range using integer math, rounding to the nearest value,
+// so that the endpoints of each map exactly onto the endpoints of the other.
+fn axis_value_to_i16(range: RangeInclusive<i32>, raw: i32) -> Result<i16, GetAxisError> {
+	if !range.contains(&raw) {
+		return Err(GetAxisError::Value);
+	}
+
+	check_span(&range)?;
+
+	let (lo, hi) = (*range.start() as i64, *range.end() as i64);
+	let span = hi - lo;
+	let steps = u16::MAX as i64;
+	let offset = ((raw as i64 - lo) * steps + span / 2) / span;
+	Ok((i16::MIN as i64 + offset) as i16)
+}
+
+fn i16_to_axis_value(range: RangeInclusive<i32>, value: i16) -> Result<i32, SetAxisError> {
+	check_span(&range)?;
+
+	let (lo, hi) = (*range.start() as i64, *range.end() as i64);
+	let span = hi - lo;
+
+	let steps = u16::MAX as i64;
+	let offset = ((value as i64 - i16::MIN as i64) * span + steps / 2) / steps;
+	Ok((lo + offset) as i32)
+}
+
+fn normalize_axis_value(range: RangeInclusive<i32>, raw: i32) -> Result<f32, GetAxisError> {
+	if !range.contains(&raw) {
+		return Err(GetAxisError::Value);
+	}
+
+	check_span(&range)?;
+
+	let (lo, hi) = range.into_inner();
+	let offset = raw.wrapping_sub(lo) as u32;
+	let span = hi.wrapping_sub(lo) as u32;
+
+	Ok(offset as f32 / span as f32)
+}
+
+/// An `OwnedDeviceSlot` is a `DeviceSlot` which has been acquired by this process. Its `set_*`
+/// methods only write a buffered state, which is sent to the driver by `apply`.
 #[derive(Debug)]
 pub struct OwnedDeviceSlot {
 	slot: DeviceSlot,
-	state: RefCell<vjoy_sys::JOYSTICK_POSITION>,
+
+	// The buffered state is kept in a `Cell` and copied in and out as a whole, rather than borrowed
+	// through a `RefCell`. This way, no borrow is ever held while other code runs, so reentrant
+	// calls (e.g. a setter called from within a callback) can never panic on a double borrow.
+	state: Cell<DeviceState>,
+	dirty: Cell<DirtyFlags>,
+
+	// Bit mask of the buttons set with `set_button_momentary`, which are released after the next
+	// successful `apply`.
+	momentary: Cell<u128>,
+
+	// Axis ranges, indexed by `Axis as usize`, cached as they are first retrieved.
+	ranges: [Cell<Option<(i32, i32)>>; AXIS_COUNT],
+
+	// A copy of the state which was last successfully sent to the device, for `apply_if_changed`.
+	applied: Cell<Option<DeviceState>>,
+
+	// The device's count of forced relinquishes when it was acquired. If that count has since
+	// changed, the device is no longer owned by this slot, and must not be relinquished on drop.
+	forced_relinquishes: u64,
 }
 
 impl OwnedDeviceSlot {
 	fn new(slot: DeviceSlot) -> OwnedDeviceSlot {
 		OwnedDeviceSlot {
-			state: RefCell::new(vjoy_sys::JOYSTICK_POSITION {
-				bDevice: slot.id.try_into()
-					.expect("vJoy device ID does not fit into JOYSTICK_POSITION structure"),
+			state: Cell::new(DeviceState::new(slot.id)),
+			dirty: Cell::new(DirtyFlags::default()),
+			momentary: Cell::new(0),
+			ranges: Default::default(),
+			applied: Cell::new(None),
+			forced_relinquishes: forced_relinquishes(slot.id),
+
+			slot,
+		}
+	}
+
+	/// Gets the range of an axis, as `DeviceSlot::axis_range` does. The range of each axis is only
+	/// retrieved from the driver the first time that it is needed, and is cached afterwards.
+	pub fn axis_range(&self, axis: Axis) -> Result<RangeInclusive<i32>, AxisRangeError> {
+		let cached = &self.ranges[axis as usize];
+		if let Some((lo, hi)) = cached.get() {
+			return Ok(lo..=hi);
+		}
+
+		let range = self.slot.axis_range(axis)?;
+		cached.set(Some((*range.start(), *range.end())));
+		Ok(range)
+	}
+
+	/// Clears the cached axis ranges, so that they will be retrieved from the driver again.
+	pub fn invalidate_axis_ranges(&self) {
+		for cached in &self.ranges {
+			cached.set(None);
+		}
+	}
+
+	/// Sends the buffered state to the vJoy device. This is equivalent to `apply`.
+	pub fn flush(&self) -> Result<(), ApplyError> {
+		self.apply()
+	}
+
+	/// Returns an iterator over each supported axis and its normalized value, skipping failures.
+	pub fn axis_values(&self) -> impl Iterator<Item = (Axis, f32)> + '_ {
+		let state = self.state.get();
+
+		self.axes()
+			.filter_map(move |a| {
+				let range = self.axis_range(a).ok()?;
+				normalize_axis_value(range, state.axis(a)).ok()
+					.map(|v| (a, v))
+			})
+	}
+
+	/// Returns an iterator over each of the device's axes, along with its value in the buffered state
+	/// and its (cached) range.
+	pub fn axis_states(&self) -> impl Iterator<Item = Result<AxisState, AxisRangeError>> + '_ {
+		let state = self.state.get();
+
+		self.slot.axes()
+			.map(move |axis| Ok(AxisState {
+				axis,
+				value: state.axis(axis),
+				range: self.axis_range(axis)?,
+			}))
+	}
+
+	/// Returns the raw values of all axes in the buffered state, in the order of `Axis::ALL`. This
+	/// includes axes which the device does not support.
+	pub fn axis_raw_values(&self) -> [i32; AXIS_COUNT] {
+		let state = self.state.get();
+		Axis::ALL.map(|a| state.axis(a))
+	}
+
+	pub fn get_axis_f32(&self, axis: Axis) -> Result<f32, GetAxisError> {
+		normalize_axis_value(self.axis_range(axis)?, self.get_axis_raw(axis))
+	}
+
+	/// Gets the value of an axis in `-1.0..=1.0`, where -1.0 is the minimum of the axis's range, 0.0
+	/// is its center, and 1.0 is its maximum.
+	pub fn get_axis_signed(&self, axis: Axis) -> Result<f32, GetAxisError> {
+		let range = self.axis_range(axis)?;
+		axis_value_to_signed(range, self.get_axis_raw(axis))
+	}
+
+	/// Gets the value of an axis mapped onto the full range of `i16`.
+	pub fn get_axis_i16(&self, axis: Axis) -> Result<i16, GetAxisError> {
+		let range = self.axis_range(axis)?;
+		axis_value_to_i16(range, self.get_axis_raw(axis))
+	}
+
+	pub fn get_axis_raw(&self, axis: Axis) -> i32 {
+		self.state.get().axis(axis)
+	}
+
+	/// Returns a continuous POV's angle in centidegrees, or `Some(None)` if it is centered.
+	pub fn get_cont_pov(&self, index: usize) -> Option<Option<u16>> {
+		self.state.get().cont_pov(index)
+	}
+
+	/// Returns the angle of a continuous POV in the buffered state in degrees, or `None` if the index
+	/// is beyond the POVs that vJoy supports. An inner `None` indicates that the POV is neutral.
+	pub fn get_cont_pov_degrees(&self, index: usize) -> Option<Option<f32>> {
+		self.get_cont_pov(index).map(|v| v.map(|c| f32::from(c) / 100.0))
+	}
+
+	/// Returns the direction of a discrete POV in the buffered state, or `None` if the index is
+	/// beyond the POVs that vJoy supports.
+	pub fn get_disc_pov(&self, index: usize) -> Option<PovDirection> {
+		self.state.get().disc_pov(index)
+	}
+
+	pub fn get_button(&self, index: usize) -> Option<bool> {
+		self.state.get().button(index)
+	}
+
+	/// Returns an iterator over each of the device's buttons and its state, or fails if the number of
+	/// buttons cannot be retrieved.
+	pub fn buttons(&self) -> Result<impl Iterator<Item = (usize, bool)>, NumButtonsError> {
+		let state = self.state.get();
+
+		Ok((0..self.num_buttons()?)
+			.map(move |i| (i, state.button(i).unwrap_or(false))))
+	}
+
+	/// Returns the states of all buttons packed into a bit mask, where bit N is button N.
+	pub fn buttons_mask(&self) -> u128 {
+		self.state.get().buttons_mask()
+	}
+
+	/// Gets the state of a button in the buffered state like `get_button`, but fails if the device
+	/// does not have the button, in the same way as `set_button_checked`.
+	pub fn get_button_checked(&self, index: usize) -> Result<bool, SetButtonError> {
+		self.has_button(index)
+			.then(|| self.get_button(index))
+			.flatten()
+			.ok_or(SetButtonError::NoSuchButton)
+	}
+
+	/// Returns which categories of inputs in the buffered state have been modified since it was last
+	/// applied.
+	pub fn dirty_categories(&self) -> DirtyFlags {
+		self.dirty.get()
+	}
+
+	/// Returns whether the buffered state has been modified since it was last applied.
+	pub fn is_dirty(&self) -> bool {
+		self.dirty.get().any()
+	}
+
+	/// Sets an axis like `set_axis_f32`, but with a deadzone around the center.
+	pub fn set_axis_f32_deadzone(&self, axis: Axis, value: f32, deadzone: f32) -> Result<(), SetAxisError> {
+		if !(0.0..1.0).contains(&deadzone) {
+			return Err(SetAxisError::Deadzone);
+		}
+
+		if !(0.0..=1.0).contains(&value) {
+			return Err(SetAxisError::Value);
+		}
+
+		let signed = 2.0 * value - 1.0;
+		let magnitude = ((signed.abs() - deadzone) / (1.0 - deadzone)).clamp(0.0, 1.0);
+		self.set_axis_signed(axis, magnitude.copysign(signed))
+	}
+
+	/// Sets an axis like `set_axis_f32`, but snapped to the nearest of `detents` even positions.
+	pub fn set_axis_detented(&self, axis: Axis, value: f32, detents: usize) -> Result<(), SetAxisError> {
+		if detents == 0 {
+			return Err(SetAxisError::Detents);
+		}
+
+		if !(-AXIS_F32_TOLERANCE..=1.0 + AXIS_F32_TOLERANCE).contains(&value) {
+			return Err(SetAxisError::Value);
+		}
+
+		let snapped = if detents == 1 {
+			0.5
+		} else {
+			let steps = (detents - 1) as f32;
+			f32::round(value.clamp(0.0, 1.0) * steps) / steps
+		};
+
+		self.set_axis_f32_strict(axis, snapped)
+	}
+
+	/// Applies the buffered state if the rate limiter allows it, returning whether it was applied. If
+	/// it was throttled, the buffered state is left as-is to be applied later.
+	pub fn apply_limited(&self, limiter: &mut RateLimiter) -> Result<bool, ApplyError> {
+		if !limiter.try_tick() {
+			return Ok(false);
+		}
+
+		self.apply()?;
+		Ok(true)
+	}
+
+	/// Returns when a force-feedback packet was last sent to this device. This is only tracked
+	/// while an `FfbPackets` subscription exists.
+	pub fn last_ffb_at(&self) -> Option<Instant> {
+		crate::ffb::last_packet_at(self.id())
+	}
+
+	/// Plays a timeline onto the device, blocking until it completes (`Ok(true)`) or `cancel` is
+	/// set (`Ok(false)`). With `looping`, it only ends when cancelled.
+	pub fn play(&self, timeline: &Timeline, looping: bool, cancel: Option<&AtomicBool>) -> Result<bool, ApplyError> {
+		let cancelled = || cancel.is_some_and(|c| c.load(Ordering::Relaxed));
+
+		loop {
+			let start = Instant::now();
+
+			for (at, state) in timeline.entries() {
+				if let Some(pause) = (start + *at).checked_duration_since(Instant::now()) {
+					std::thread::sleep(pause);
+				}
+
+				if cancelled() {
+					return Ok(false);
+				}
+
+				self.restore(state);
+				self.apply()?;
+			}
+
+			if !looping || timeline.is_empty() {
+				return Ok(true);
+			}
+
+			if cancelled() {
+				return Ok(false);
+			}
+		}
+	}
+
+	/// Records the buffered state into a timeline every `interval` until `duration` elapses.
+	pub fn record(&self, duration: Duration, interval: Duration) -> Timeline {
+		let start = Instant::now();
+		let mut timeline = Timeline::new();
+		let mut at = Duration::ZERO;
+
+		while at <= duration {
+			if let Some(pause) = (start + at).checked_duration_since(Instant::now()) {
+				std::thread::sleep(pause);
+			}
+
+			timeline.push(at, self.snapshot());
+
+			if interval.is_zero() {
+				break;
+			}
+
+			at += interval;
+		}
+
+		timeline
+	}
+
+	/// Copies the axes, buttons, and POVs in another device's buffered state into this device's
+	/// buffered state. Only the inputs which this device has are copied; the rest are left as-is.
+	pub fn copy_state_from(&self, other: &OwnedDeviceSlot) {
+		let source = other.state.get();
+		let axes = self.axes().collect::<Vec<_>>();
+
+		let num_buttons = self.num_buttons().unwrap_or(0);
+		let num_cont_pov = self.num_cont_pov().unwrap_or(0);
+		let num_disc_pov = self.num_disc_pov().unwrap_or(0);
+
+		self.with_state(|s| {
+			for axis in axes {
+				s.set_axis(axis, source.axis(axis));
+			}
+
+			let button_mask = u128::MAX.checked_shr(MAX_BUTTONS as u32 - num_buttons as u32).unwrap_or(0);
+			s.set_buttons_mask((s.buttons_mask() & !button_mask) | (source.buttons_mask() & button_mask));
+
+			for i in 0..num_cont_pov {
+				if let Some(value) = source.cont_pov(i) {
+					s.set_cont_pov(i, value).ok();
+				}
+			}
+
+			for i in 0..num_disc_pov {
+				if let Some(dir) = source.disc_pov(i) {
+					s.set_disc_pov(i, dir).ok();
+				}
+			}
+		});
+	}
+
+	/// Overwrites the buffered state with a copy of the given state (e.g. one previously returned by
+	/// `snapshot`). The state is retargeted to this device.
+	pub fn restore(&self, state: &DeviceState) {
+		self.with_state(|s| *s = *state);
+	}
+
+	/// Returns a copy of the buffered state, which may later be passed to `restore`.
+	pub fn snapshot(&self) -> DeviceState {
+		self.state.get()
+	}
+
+	/// Sets an axis to a normalized value, from 0.0 at the minimum to 1.0 at the maximum. Values
+	/// outside of that by up to `AXIS_F32_TOLERANCE` are clamped rather than rejected.
+	pub fn set_axis_f32(&self, axis: Axis, value: f32) -> Result<(), SetAxisError> {
+		let raw = self.denormalize_axis_tolerant(axis, value)?;
+		self.set_axis(axis, raw);
+		Ok(())
+	}
+
+	/// Sets an axis like `set_axis_f32`, but clamps out-of-range values rather than rejecting them.
+	pub fn set_axis_f32_clamped(&self, axis: Axis, value: f32) -> Result<f32, SetAxisError> {
+		if value.is_nan() {
+			return Err(SetAxisError::Value);
+		}
+
+		let value = value.clamp(0.0, 1.0);
+		self.set_axis_f32_strict(axis, value)?;
+		Ok(value)
+	}
+
+	fn denormalize_axis_tolerant(&self, axis: Axis, value: f32) -> Result<i32, SetAxisError> {
+		if !(-AXIS_F32_TOLERANCE..=1.0 + AXIS_F32_TOLERANCE).contains(&value) {
+			return Err(SetAxisError::Value);
+		}
+
+		denormalize_axis_value(self.axis_range(axis)?, value.clamp(0.0, 1.0))
+	}
+
+	/// Sets several axes like `set_axis_f32`. If any value is invalid, no axis is changed.
+	pub fn set_axes_f32(&self, values: &[(Axis, f32)]) -> Result<(), SetAxisError> {
+		let raw = values.iter()
+			.map(|&(axis, value)| self.denormalize_axis_tolerant(axis, value).map(|v| (axis, v)))
+			.collect::<Result<Vec<_>, _>>()?;
+
+		self.set_axes(&raw);
+		Ok(())
+	}
+
+	/// Sets several axes like `set_axis_raw`. If any value is invalid, no axis is changed.
+	pub fn set_axes_raw(&self, values: &[(Axis, i32)]) -> Result<(), SetAxisError> {
+		for &(axis, value) in values {
+			if !self.axis_range(axis)?.contains(&value) {
+				return Err(SetAxisError::Value);
+			}
+		}
+
+		self.set_axes(values);
+		Ok(())
+	}
+
+	fn set_axes(&self, values: &[(Axis, i32)]) {
+		self.with_state(|s| {
+			for &(axis, value) in values {
+				s.set_axis(axis, value);
+			}
+		});
+	}
+
+	/// Sets an axis to a normalized value, where 0.0 is the minimum of the axis's range and 1.0 is
+	/// its maximum. Unlike `set_axis_f32`, any value outside of that range is rejected.
+	pub fn set_axis_f32_strict(&self, axis: Axis, value: f32) -> Result<(), SetAxisError> {
+		let raw = denormalize_axis_value(self.axis_range(axis)?, value)?;
+		self.set_axis(axis, raw);
+		Ok(())
+	}
+
+	/// Sets the raw values of all axes in the order of `Axis::ALL`, without checking their ranges.
+	pub fn set_axis_raw_values(&self, values: [i32; AXIS_COUNT]) {
+		self.with_state(|s| {
+			for (axis, value) in Axis::all().zip(values) {
+				s.set_axis(axis, value);
+			}
+		});
+	}
+
+	/// Sets an axis to a value in `-1.0..=1.0`, where 0.0 is the center of its range.
+	pub fn set_axis_signed(&self, axis: Axis, value: f32) -> Result<(), SetAxisError> {
+		let range = self.axis_range(axis)?;
+		self.set_axis(axis, signed_to_axis_value(range, value)?);
+		Ok(())
+	}
+
+	/// Sets an axis from a value spanning the full range of `i16`.
+	pub fn set_axis_i16(&self, axis: Axis, value: i16) -> Result<(), SetAxisError> {
+		let range = self.axis_range(axis)?;
+		self.set_axis(axis, i16_to_axis_value(range, value)?);
+		Ok(())
+	}
+
+	pub fn set_axis_raw(&self, axis: Axis, value: i32) -> Result<(), SetAxisError> {
+		let range = self.axis_range(axis)?;
+		if !range.contains(&value) {
+			return Err(SetAxisError::Value);
+		}
+
+		self.set_axis(axis, value);
+		Ok(())
+	}
+
+	/// Sets an axis to a raw value like `set_axis_raw`, but clamps any value outside of the axis's
+	/// range into it rather than rejecting it. Returns the clamped value which was written.
+	pub fn set_axis_raw_clamped(&self, axis: Axis, value: i32) -> Result<i32, SetAxisError> {
+		let value = clamp_axis_value(&self.axis_range(axis)?, value as i64);
+		self.set_axis(axis, value);
+		Ok(value)
+	}
+
+	fn set_axis(&self, axis: Axis, value: i32) {
+		self.with_state(|s| s.set_axis(axis, value));
+	}
+
+	/// Sets the angle of a continuous POV in the buffered state in centidegrees (`0..=35999`), or
+	/// centers it if `None` is given.
+	pub fn set_cont_pov(&self, index: usize, value: Option<u16>) -> Result<(), SetPovError> {
+		if !self.num_cont_pov().is_ok_and(|n| index < n) {
+			return Err(SetPovError::NoSuchPov);
+		}
+
+		self.with_state(|s| s.set_cont_pov(index, value))
+	}
+
+	/// Sets the angle of a continuous POV in the buffered state in degrees, or makes it neutral if
+	/// `None` is given. Angles outside of 0..360 are wrapped into it.
+	pub fn set_cont_pov_degrees(&self, index: usize, degrees: Option<f32>) -> Result<(), SetPovError> {
+		let value = match degrees {
+			Some(d) if !d.is_finite() => return Err(SetPovError::Angle),
+			Some(d) => Some(((d.rem_euclid(360.0) * 100.0).round() as u16) % 36000),
+			None => None,
+		};
+
+		self.set_cont_pov(index, value)
+	}
+
+	/// Sets a discrete POV. Mixing this with `set_cont_pov` on one device is undefined.
+	pub fn set_disc_pov(&self, index: usize, dir: PovDirection) -> Result<(), SetPovError> {
+		if !self.num_disc_pov().is_ok_and(|n| index < n) {
+			return Err(SetPovError::NoSuchPov);
+		}
+
+		self.with_state(|s| s.set_disc_pov(index, dir))
+	}
+
+	/// Sets all POVs to their neutral (centered) position in the buffered state, without resetting
+	/// the device itself.
+	pub fn set_pov_neutral_all(&self) {
+		self.with_state(|s| s.center_povs());
+	}
+
+	/// Sets axes in the order of `Axis::ALL` from values in `-1.0..=1.0`, skipping any the device
+	/// lacks. If any value is invalid, no axis is changed.
+	pub fn set_all_axes_bipolar(&self, values: &[f32]) -> Result<(), SetAxisError> {
+		if values.len() > AXIS_COUNT {
+			return Err(SetAxisError::Length);
+		}
+
+		// Every value is checked, including those for axes the device lacks, and without the
+		// tolerance that `set_axes_f32` allows.
+		if !values.iter().all(|v| (-1.0..=1.0).contains(v)) {
+			return Err(SetAxisError::Value);
+		}
+
+		let normalized = Axis::ALL.iter().zip(values)
+			.filter(|(&axis, _)| self.has_axis(axis))
+			.map(|(&axis, &value)| (axis, 0.5 * (value + 1.0)))
+			.collect::<Vec<_>>();
+
+		self.set_axes_f32(&normalized)
+	}
+
+	/// Sets an axis from throttle and brake inputs, as `0.5 + 0.5 * throttle - 0.5 * brake`.
+	pub fn set_combined_axis(&self, axis: Axis, throttle: f32, brake: f32) -> Result<(), SetAxisError> {
+		let throttle = throttle.clamp(0.0, 1.0);
+		let brake = brake.clamp(0.0, 1.0);
+		self.set_axis_f32(axis, 0.5 + 0.5 * throttle - 0.5 * brake)
+	}
+
+	/// Sets the state of a button in the buffered state. With `strict`, writes to buttons beyond
+	/// the device's button count are dropped.
+	pub fn set_button<B: ToButtonIndex>(&self, button: B, value: bool) -> Result<(), SetButtonError> {
+		let index = button.to_button_index();
+
+		#[cfg(feature = "strict")]
+		if index < MAX_BUTTONS && !self.has_button(index) {
+			#[cfg(feature = "tracing")]
+			tracing::warn!(device = %self.id, index, "dropping write to a button the vJoy device does not have");
+
+			return Ok(());
+		}
+
+		self.with_state(|s| s.set_button(index, value))
+	}
+
+	/// Sets a button like `set_button`, but fails if the device does not have it.
+	pub fn set_button_checked<B: ToButtonIndex>(&self, button: B, value: bool) -> Result<(), SetButtonError> {
+		let index = button.to_button_index();
+		if !self.has_button(index) {
+			return Err(SetButtonError::NoSuchButton);
+		}
+
+		self.with_state(|s| s.set_button(index, value))
+	}
+
+	/// Presses a button for a single frame; it is released after the next successful `apply`.
+	pub fn set_button_momentary<B: ToButtonIndex>(&self, button: B) -> Result<(), SetButtonError> {
+		let index = button.to_button_index();
+		self.set_button(index, true)?;
+
+		// With `strict`, the press was dropped if the device lacks the button, so there is nothing
+		// to release.
+		if cfg!(feature = "strict") && !self.has_button(index) {
+			return Ok(());
+		}
+
+		self.momentary.set(self.momentary.get() | (1 << index));
+		Ok(())
+	}
+
+	/// Centers all axes and POVs and releases all buttons, then applies the state. Axes whose range
+	/// cannot be retrieved are left as-is rather than failing.
+	pub fn neutral(&self) -> Result<(), ApplyError> {
+		self.center_state();
+		self.apply()
+	}
+
+	/// Resets the vJoy device and the buffered state, centering supported axes and all POVs.
+	pub fn reset(&self) -> Result<(), ResetError> {
+		let success = unsafe { ffi::ResetVJD(self.id.to_raw() as u32) } != 0;
+		success.then_some(()).ok_or(ResetError::Failed)?;
+
+		self.center_state();
+		Ok(())
+	}
+
+	/// Releases all buttons on the vJoy device and in the buffered state, leaving axes and POVs
+	/// untouched.
+	pub fn reset_buttons(&self) -> Result<(), ResetError> {
+		let success = unsafe { ffi::ResetButtons(self.id.to_raw() as u32) } != 0;
+		success.then_some(()).ok_or(ResetError::Failed)?;
+
+		self.with_state(|s| s.clear_buttons());
+		self.dirty.set(DirtyFlags { buttons: false, ..self.dirty.get() });
+		Ok(())
+	}
+
+	/// Centers all POVs on the vJoy device and in the buffered state, leaving axes and buttons
+	/// untouched.
+	pub fn reset_povs(&self) -> Result<(), ResetError> {
+		let success = unsafe { ffi::ResetPovs(self.id.to_raw() as u32) } != 0;
+		success.then_some(()).ok_or(ResetError::Failed)?;
+
+		self.set_pov_neutral_all();
+		self.dirty.set(DirtyFlags { povs: false, ..self.dirty.get() });
+		Ok(())
+	}
+
+	fn center_state(&self) {
+		let centers = self.axes()
+			.filter_map(|a| self.axis_range(a).ok().map(|r| (a, axis_center(&r))))
+			.collect::<Vec<_>>();
+
+		self.with_state(|s| {
+			for (axis, value) in centers {
+				s.set_axis(axis, value);
+			}
+
+			s.clear_buttons();
+		});
+
+		self.set_pov_neutral_all();
+	}
+
+	/// Sets the states of all buttons in the buffered state from a bit mask, with the same bit order
+	/// as `buttons_mask`. The mask is written as-is, even with the `strict` feature enabled.
+	pub fn set_buttons_mask(&self, mask: u128) {
+		self.with_state(|s| s.set_buttons_mask(mask));
+	}
+
+	/// Sets several buttons in order, stopping at the first invalid index. Earlier ones stay set.
+	pub fn set_buttons<I: IntoIterator<Item = (usize, bool)>>(&self, buttons: I) -> Result<(), SetButtonsError> {
+		#[cfg(feature = "strict")]
+		let num_buttons = self.num_buttons().unwrap_or(0);
+
+		self.with_state(|state| {
+			for (index, value) in buttons {
+				#[cfg(feature = "strict")]
+				if index < MAX_BUTTONS && index >= num_buttons {
+					#[cfg(feature = "tracing")]
+					tracing::warn!(device = %self.id, index, "dropping write to a button the vJoy device does not have");
 
-				..unsafe { std::mem::zeroed() }
-			}),
+					continue;
+				}
 
-			slot,
-		}
+				state.set_button(index, value)
+					.map_err(|error| SetButtonsError { index, error })?;
+			}
+
+			Ok(())
+		})
 	}
 
-	pub fn get_axis_f32(&self, axis: Axis) -> Result<f32, GetAxisError> {
-		let range = self.axis_range(axis)?;
-		let raw = self.get_axis_raw(axis);
+	/// Presses a button and applies, waits for `hold`, then releases it and applies again.
+	pub fn press_and_release(&self, index: usize, hold: Duration) -> Result<(), PressAndReleaseError> {
+		self.set_button(index, true)?;
 
-		if !range.contains(&raw) {
-			return Err(GetAxisError::Value);
+		// If the press cannot be sent, the button is released again so that it is not stuck pressed
+		// by the next unrelated `apply`.
+		if let Err(e) = self.apply() {
+			self.set_button(index, false)?;
+			return Err(e.into());
 		}
 
-		let (lo, hi) = range.into_inner();
-		let offset = raw.wrapping_sub(lo) as u32;
-		let span = hi.wrapping_sub(lo) as u32;
+		std::thread::sleep(hold);
 
-		Ok(offset as f32 / span as f32)
+		self.set_button(index, false)?;
+		self.apply()?;
+		Ok(())
 	}
 
-	pub fn get_axis_raw(&self, axis: Axis) -> i32 {
-		let state = self.state.borrow();
-		match axis {
-			Axis::X => state.wAxisX,
-			Axis::Y => state.wAxisY,
-			Axis::Z => state.wAxisZ,
-			Axis::RX => state.wAxisXRot,
-			Axis::RY => state.wAxisYRot,
-			Axis::RZ => state.wAxisZRot,
-			Axis::Slider => state.wSlider,
-			Axis::Dial => state.wDial,
-
-			Axis::Accelerator => state.wAccelerator,
-			Axis::Aileron => state.wAileron,
-			Axis::Brake => state.wBrake,
-			Axis::Clutch => state.wClutch,
-			Axis::Rudder => state.wRudder,
-			Axis::Steering => state.wSteering,
-			Axis::Throttle => state.wThrottle,
-			Axis::Wheel => state.wWheel,
+	/// Returns a raw pointer to the buffered state, for passing to `vjoy_sys::UpdateVJD` directly.
+	/// It may only be dereferenced on this device's thread while no other method of it runs, and
+	/// `bDevice` must not be modified. Changes made through it are not tracked by `is_dirty`.
+	pub fn state_ptr(&self) -> *mut vjoy_sys::JOYSTICK_POSITION {
+		self.state.as_ptr() as *mut vjoy_sys::JOYSTICK_POSITION
+	}
+
+	/// Runs a closure on the raw buffered state, as `with_state` does. Changes to `bDevice` are
+	/// discarded, as are raw-only fields if the device is modified from within the closure.
+	pub fn with_raw_state<R>(&self, f: impl FnOnce(&mut vjoy_sys::JOYSTICK_POSITION) -> R) -> R {
+		self.with_state(|s| f(s.raw_mut()))
+	}
+
+	/// Runs a closure with mutable access to the buffered state, marking the changed inputs dirty.
+	/// Changes made to the device from within the closure are kept unless the closure overrides them.
+	pub fn with_state<R>(&self, f: impl FnOnce(&mut DeviceState) -> R) -> R {
+		let before = self.state.get();
+
+		let mut state = before;
+		let result = f(&mut state);
+		state.retarget(self.id);
+
+		let live = self.state.get();
+		if live.diff(&before).any() {
+			let mut merged = live;
+			merged.merge_changes(&before, &state);
+			state = merged;
 		}
+
+		self.state.set(state);
+		self.dirty.set(self.dirty.get() | before.diff(&state));
+		result
 	}
 
-	pub fn get_button(&self, index: usize) -> Option<bool> {
-		let state = self.state.borrow();
+	/// Relinquishes and re-acquires this device (e.g. after a driver restart), restoring its state.
+	/// If sending the restored state fails, it stays buffered for the next `apply`.
+	pub fn reacquire(self) -> Result<OwnedDeviceSlot, (DeviceSlot, AcquireError)> {
+		let slot = self.slot.clone();
+		let state = self.state.get();
+		drop(self);
 
-		let (word, bit) = match index {
-			 0..= 31 => Some((&state.lButtons,    index)),
-			32..= 63 => Some((&state.lButtonsEx1, index - 32)),
-			64..= 95 => Some((&state.lButtonsEx2, index - 64)),
-			96..=127 => Some((&state.lButtonsEx3, index - 96)),
-			_ => None,
-		}?;
+		let owned = slot.try_acquire()?;
+		owned.state.set(state);
+		owned.dirty.set(DirtyFlags::ALL);
+		owned.apply().ok();
 
-		Some((*word & (1 << bit)) != 0)
+		Ok(owned)
 	}
 
-	pub fn set_axis_f32(&self, axis: Axis, value: f32) -> Result<(), SetAxisError> {
-		if !(0.0..=1.0).contains(&value) {
-			return Err(SetAxisError::Value);
-		}
+	pub fn relinquish(self) {}
 
-		let (lo, hi) = self.axis_range(axis)?.into_inner();
-		let span = hi.wrapping_sub(lo) as u32;
+	pub fn apply(&self) -> Result<(), ApplyError> {
+		let mut state = self.state.get();
+        let success = unsafe { ffi::UpdateVJD(self.id.to_raw() as u32, state.raw_mut() as *mut _ as *mut _) } != 0;
+		success.then_some(()).ok_or(ApplyError::Failed)?;
+
+		self.applied.set(Some(state));
+		self.dirty.set(DirtyFlags::default());
+
+		let momentary = self.momentary.replace(0);
+		if momentary != 0 {
+			self.with_state(|s| {
+				for index in (0..MAX_BUTTONS).filter(|i| momentary & (1 << i) != 0) {
+					s.set_button(index, false).ok();
+				}
+			});
+		}
 
-		self.set_axis(axis, lo + f32::round(span as f32 * value) as i32);
 		Ok(())
 	}
 
-	pub fn set_axis_raw(&self, axis: Axis, value: i32) -> Result<(), SetAxisError> {
-		let range = self.axis_range(axis)?;
-		if !range.contains(&value) {
-			return Err(SetAxisError::Value);
+	/// Applies like `apply`, but falls back to `neutral` on failure, returning the original error.
+	///
+	/// The fallback recenters the buffered state itself before sending it, so whatever input was
+	/// pending in the buffered state when the apply failed is discarded.
+	pub fn apply_checked(&self) -> Result<(), ApplyError> {
+		let result = self.apply();
+		if result.is_err() {
+			self.neutral().ok();
 		}
 
-		self.set_axis(axis, value);
-		Ok(())
+		result
 	}
 
-	fn set_axis(&self, axis: Axis, value: i32) {
-		let mut state = self.state.borrow_mut();
-		match axis {
-			Axis::X => state.wAxisX = value,
-			Axis::Y => state.wAxisY = value,
-			Axis::Z => state.wAxisZ = value,
-			Axis::RX => state.wAxisXRot = value,
-			Axis::RY => state.wAxisYRot = value,
-			Axis::RZ => state.wAxisZRot = value,
-			Axis::Slider => state.wSlider = value,
-			Axis::Dial => state.wDial = value,
-
-			Axis::Accelerator => state.wAccelerator = value,
-			Axis::Aileron => state.wAileron = value,
-			Axis::Brake => state.wBrake = value,
-			Axis::Clutch => state.wClutch = value,
-			Axis::Rudder => state.wRudder = value,
-			Axis::Steering => state.wSteering = value,
-			Axis::Throttle => state.wThrottle = value,
-			Axis::Wheel => state.wWheel = value,
-		}
-	}
-
-	pub fn set_button(&self, index: usize, value: bool) -> Result<(), SetButtonError> {
-		let mut state = self.state.borrow_mut();
-
-		let (word, bit) = match index {
-			 0..= 31 => Ok((&mut state.lButtons,    index)),
-			32..= 63 => Ok((&mut state.lButtonsEx1, index - 32)),
-			64..= 95 => Ok((&mut state.lButtonsEx2, index - 64)),
-			96..=127 => Ok((&mut state.lButtonsEx3, index - 96)),
-			_ => Err(SetButtonError::NoSuchButton),
-		}?;
-
-		let mask = 1 << bit;
-		*word = if value { *word | mask } else { *word & !mask };
-		Ok(())
+	/// Sends the buffered state to the vJoy device only if it has been modified since it was last
+	/// applied, returning whether an update was sent.
+	pub fn apply_if_dirty(&self) -> Result<bool, ApplyError> {
+		if !self.is_dirty() {
+			return Ok(false);
+		}
+
+		self.apply()?;
+		Ok(true)
 	}
 
-	pub fn relinquish(self) {}
+	/// Applies only if the buffered state differs from the last one sent, returning whether it did.
+	pub fn apply_if_changed(&self) -> Result<bool, ApplyError> {
+		if self.applied.get().is_some_and(|a| !a.diff(&self.state.get()).any()) {
+			return Ok(false);
+		}
 
-	pub fn apply(&self) -> Result<(), ApplyError> {
-		let state = self.state.borrow();
-        let success = unsafe { vjoy_sys::UpdateVJD(self.id.to_raw() as u32, state.deref() as *const _ as *mut _) } != 0;
-		success.then_some(()).ok_or(ApplyError::Failed)
+		self.apply()?;
+		Ok(true)
 	}
 }
 
@@ -335,11 +1253,64 @@ impl Deref for OwnedDeviceSlot {
 
 impl Drop for OwnedDeviceSlot {
 	fn drop(&mut self) {
-		unsafe { vjoy_sys::RelinquishVJD(self.id.to_raw() as u32); }
+		if forced_relinquishes(self.id) == self.forced_relinquishes {
+			unsafe { ffi::RelinquishVJD(self.id.to_raw() as u32); }
+		}
+	}
+}
+
+// How many times each device (indexed by `DeviceId::to_index`) has been relinquished by
+// `force_relinquish` rather than by dropping its `OwnedDeviceSlot`.
+static FORCED_RELINQUISHES: Mutex<[u64; MAX_DEVICES]> = Mutex::new([0; MAX_DEVICES]);
+
+fn forced_relinquishes(id: DeviceId) -> u64 {
+	let counts = FORCED_RELINQUISHES.lock().unwrap();
+	counts.get(id.to_index()).copied().unwrap_or(0)
+}
+
+/// Relinquishes a device regardless of whether an `OwnedDeviceSlot` for it exists. Any such slot
+/// will not relinquish the device again when dropped, since it may have been re-acquired by then.
+pub(crate) fn force_relinquish(id: DeviceId) {
+	if let Some(count) = FORCED_RELINQUISHES.lock().unwrap().get_mut(id.to_index()) {
+		*count += 1;
+	}
+
+	unsafe { ffi::RelinquishVJD(id.to_raw() as u32); }
+}
+
+/// `ToButtonIndex` converts a value into the zero-based index of a vJoy button.
+pub trait ToButtonIndex {
+	fn to_button_index(self) -> usize;
+}
+
+impl ToButtonIndex for usize {
+	fn to_button_index(self) -> usize {
+		self
 	}
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd)]
+/// The number of distinct axes that a vJoy device may have.
+pub const AXIS_COUNT: usize = 16;
+
+pub(crate) const STANDARD_AXIS_RANGE: RangeInclusive<i32> = 0..=vjoy_sys::VJOY_AXIS_MAX_VALUE as i32;
+
+/// The maximum number of device slots that the vJoy driver supports.
+pub(crate) const MAX_DEVICES: usize = vjoy_sys::VJOY_MAX_N_DEVICES as usize;
+
+/// The maximum number of buttons that a vJoy device may have.
+pub const MAX_BUTTONS: usize = 128;
+
+/// The maximum number of POVs (of each type) that a vJoy device may have.
+pub const MAX_POVS: usize = 4;
+
+/// The largest angle, in centidegrees, that a continuous POV may be set to.
+pub const MAX_POV_CENTIDEGREES: u16 = 35999;
+
+/// The amount by which a value passed to `OwnedDeviceSlot::set_axis_f32` may fall outside of the
+/// range `0.0..=1.0` and still be accepted (after being clamped into that range).
+pub const AXIS_F32_TOLERANCE: f32 = 1e-4;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[repr(u8)]
 pub enum Axis {
 	X,
@@ -362,28 +1333,52 @@ pub enum Axis {
 }
 
 impl Axis {
+	pub const ALL: [Axis; AXIS_COUNT] = [
+		Axis::X,
+		Axis::Y,
+		Axis::Z,
+		Axis::RX,
+		Axis::RY,
+		Axis::RZ,
+		Axis::Slider,
+		Axis::Dial,
+
+		Axis::Accelerator,
+		Axis::Aileron,
+		Axis::Brake,
+		Axis::Clutch,
+		Axis::Rudder,
+		Axis::Steering,
+		Axis::Throttle,
+		Axis::Wheel,
+	];
+
+	/// Each axis paired with its HID usage, as returned by `usage`, in the order of `ALL`.
+	pub const USAGES: [(Axis, u32); AXIS_COUNT] = {
+		let mut usages = [(Axis::X, 0); AXIS_COUNT];
+
+		let mut i = 0;
+		while i < AXIS_COUNT {
+			usages[i] = (Axis::ALL[i], Axis::ALL[i].usage());
+			i += 1;
+		}
+
+		usages
+	};
+
 	pub fn all() -> impl Iterator<Item = Axis > + DoubleEndedIterator + ExactSizeIterator {
-		let axes = &[
-			Axis::X,
-			Axis::Y,
-			Axis::Z,
-			Axis::RX,
-			Axis::RY,
-			Axis::RZ,
-			Axis::Slider,
-			Axis::Dial,
-
-			Axis::Accelerator,
-			Axis::Aileron,
-			Axis::Brake,
-			Axis::Clutch,
-			Axis::Rudder,
-			Axis::Steering,
-			Axis::Throttle,
-			Axis::Wheel,
-		];
+		Axis::ALL.iter().copied()
+	}
+
+	/// Finds the axis with the given name, as returned by `name`, ignoring case.
+	pub fn from_name(name: &str) -> Option<Axis> {
+		Axis::all().find(|a| a.name().eq_ignore_ascii_case(name))
+	}
 
-		axes.iter().copied()
+	/// Finds the axis with the given HID usage, as returned by `usage`.
+	pub fn from_usage(usage: u32) -> Option<Axis> {
+		Axis::USAGES.iter()
+			.find_map(|&(a, u)| (u == usage).then_some(a))
 	}
 
 	pub fn name(&self) -> &'static str {
@@ -408,7 +1403,9 @@ impl Axis {
 		}
 	}
 
-	pub fn usage(&self) -> u32 {
+	/// The HID usage of the axis, as used by vJoy to identify it. This is stable across versions of
+	/// this crate.
+	pub const fn usage(&self) -> u32 {
 		match self {
 			Axis::X => vjoy_sys::HID_USAGE_X,
 			Axis::Y => vjoy_sys::HID_USAGE_Y,
@@ -431,7 +1428,100 @@ impl Axis {
 	}
 }
 
+impl FromStr for Axis {
+	type Err = ParseAxisError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Axis::from_name(s)
+			.ok_or(ParseAxisError::UnknownName)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Axis {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(self.name())
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Axis {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let name = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+		Axis::from_name(&name)
+			.ok_or_else(|| serde::de::Error::custom(format_args!("unknown axis name `{}`", name)))
+	}
+}
+
+/// An axis of a device, along with its value in the buffered state and its range.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct AxisState {
+	pub axis: Axis,
+	pub value: i32,
+	pub range: RangeInclusive<i32>,
+}
+
+/// The direction of a discrete (4-way) POV.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum PovDirection {
+	North,
+	East,
+	South,
+	West,
+	Neutral,
+}
+
+impl PovDirection {
+	/// Snaps an angle in centidegrees (as used by continuous POVs) to the nearest direction. Angles
+	/// exactly between two directions snap clockwise, and angles past 35999 wrap around.
+	pub fn from_centidegrees(value: u16) -> PovDirection {
+		match (value % 36000 + 4500) / 9000 {
+			1 => PovDirection::East,
+			2 => PovDirection::South,
+			3 => PovDirection::West,
+			_ => PovDirection::North,
+		}
+	}
+
+	/// Decodes a direction from the 4-bit value vJoy uses for discrete POVs. Any value other than
+	/// the four directions is treated as neutral.
+	pub fn from_nibble(value: u8) -> PovDirection {
+		match value & 0xF {
+			0 => PovDirection::North,
+			1 => PovDirection::East,
+			2 => PovDirection::South,
+			3 => PovDirection::West,
+			_ => PovDirection::Neutral,
+		}
+	}
+
+	/// Encodes this direction as the 4-bit value vJoy uses for discrete POVs.
+	pub fn to_nibble(self) -> u8 {
+		match self {
+			PovDirection::North => 0,
+			PovDirection::East => 1,
+			PovDirection::South => 2,
+			PovDirection::West => 3,
+			PovDirection::Neutral => 0xF,
+		}
+	}
+
+	/// Converts this direction to an angle in centidegrees (as used by continuous POVs), or `None`
+	/// if it is neutral.
+	pub fn to_centidegrees(self) -> Option<u16> {
+		match self {
+			PovDirection::North => Some(0),
+			PovDirection::East => Some(9000),
+			PovDirection::South => Some(18000),
+			PovDirection::West => Some(27000),
+			PovDirection::Neutral => None,
+		}
+	}
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub enum Status {
     Free,
 	Acquired,
@@ -440,6 +1530,25 @@ pub enum Status {
 	Unknown, // TODO: Should this just result in an error instead?
 }
 
+impl Status {
+	/// Returns whether a device with this status may be acquired, i.e. whether it is free.
+	pub fn is_acquirable(&self) -> bool {
+		*self == Status::Free
+	}
+}
+
+impl Display for Status {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(match self {
+			Status::Free => "Free",
+			Status::Acquired => "Acquired",
+			Status::Busy => "Busy",
+			Status::Missing => "Missing",
+			Status::Unknown => "Unknown",
+		})
+	}
+}
+
 impl From<Status> for vjoy_sys::VjdStat {
 	fn from(status: Status) -> Self {
 		match status {
@@ -467,6 +1576,15 @@ impl TryFrom<vjoy_sys::VjdStat> for Status {
 	}
 }
 
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
+pub enum AcquireError {
+	#[error("The vJoy interface failed to acquire the device (status: {0:?}).")]
+	Failed(Status),
+
+	#[error("The vJoy device does not exist.")]
+	Missing,
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
 pub enum ApplyError {
 	#[error("The vJoy interface return an error in sending the updated device state.")]
@@ -475,7 +1593,10 @@ pub enum ApplyError {
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
 pub enum AxisRangeError {
-	#[error("The vJoy Interface returned an invalid range (min >= max).")]
+	#[error("The axis's range contains only a single value, so values cannot be scaled to or from it.")]
+	Degenerate,
+
+	#[error("The vJoy Interface returned an invalid range (min > max).")]
 	Invalid,
 
 	#[error("The vJoy Interface reported an error in retrieving the axis maximum.")]
@@ -510,18 +1631,66 @@ pub enum GetAxisError {
 pub enum NumButtonsError {
 	#[error("The vJoy Interface returned an error in retrieving the number of buttons for the vJoy device.")]
 	Failed,
+
+	#[error("The vJoy Interface returned an invalid number of buttons for the vJoy device.")]
+	Invalid,
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
 pub enum NumContPovError {
 	#[error("The vJoy Interface returned an error in retrieving the number of continuous POVs for the vJoy device.")]
 	Failed,
+
+	#[error("The vJoy Interface returned an invalid number of continuous POVs for the vJoy device.")]
+	Invalid,
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
 pub enum NumDiscPovError {
 	#[error("The vJoy Interface returned an error in retrieving the number of discrete POVs for the vJoy device.")]
 	Failed,
+
+	#[error("The vJoy Interface returned an invalid number of discrete POVs for the vJoy device.")]
+	Invalid,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
+pub enum OwnerError {
+	#[error("The vJoy interface failed to get the process which owns the device (code {0}).")]
+	Failed(i32),
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
+pub enum ParseAxisError {
+	#[error("The string is not the name of an axis.")]
+	UnknownName,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
+pub enum ParseDeviceIdError {
+	#[error("The device ID is not a valid integer.")]
+	Invalid,
+
+	#[error("The device ID is too large (must be at most 255).")]
+	TooLarge,
+
+	#[error("The device ID may not be 0.")]
+	Zero,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
+pub enum PressAndReleaseError {
+	#[error(transparent)]
+	Apply(#[from] ApplyError),
+
+	#[error(transparent)]
+	Button(#[from] SetButtonError),
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
+pub enum ResetError {
+	#[error("The vJoy interface reported failure in resetting the device.")]
+	Failed,
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
@@ -529,6 +1698,15 @@ pub enum SetAxisError {
 	#[error(transparent)]
 	GetRange(#[from] AxisRangeError),
 
+	#[error("An axis deadzone must be at least 0.0 and less than 1.0.")]
+	Deadzone,
+
+	#[error("An axis must have at least one detent.")]
+	Detents,
+
+	#[error("More axis values were given than there are axes.")]
+	Length,
+
 	#[error("The value for the axis is outside of the allowed range.")]
 	Value,
 }
@@ -539,6 +1717,33 @@ pub enum SetButtonError {
 	NoSuchButton,
 }
 
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
+#[error("Failed to set button {index}: {error}")]
+pub struct SetButtonsError {
+	pub(crate) index: usize,
+	pub(crate) error: SetButtonError,
+}
+
+impl SetButtonsError {
+	pub fn error(&self) -> SetButtonError {
+		self.error
+	}
+
+	/// The index of the button which could not be set.
+	pub fn index(&self) -> usize {
+		self.index
+	}
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
+pub enum SetPovError {
+	#[error("The specified POV angle is out of range.")]
+	Angle,
+
+	#[error("The vJoy device does not support the specified POV.")]
+	NoSuchPov,
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
 pub enum TryIntoDeviceIdError {
 	#[error(transparent)]
@@ -547,3 +1752,493 @@ pub enum TryIntoDeviceIdError {
 	#[error(transparent)]
 	FromRaw(#[from] DeviceIdFromRawError),
 }
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
+pub enum WaitError {
+	#[error("The vJoy device does not exist.")]
+	Missing,
+
+	#[error("The vJoy device did not become free before the timeout elapsed.")]
+	Timeout,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[cfg(feature = "mock")]
+	use crate::mock::{TestInterface, test_device, test_slot};
+
+	#[test]
+	fn axis_names_round_trip() {
+		for axis in Axis::all() {
+			let name = axis.name();
+			assert_eq!(name.parse(), Ok(axis));
+			assert_eq!(name.to_ascii_lowercase().parse(), Ok(axis));
+			assert_eq!(name.to_ascii_uppercase().parse(), Ok(axis));
+		}
+
+		assert_eq!("".parse::<Axis>(), Err(ParseAxisError::UnknownName));
+		assert_eq!("W".parse::<Axis>(), Err(ParseAxisError::UnknownName));
+		assert_eq!(" X".parse::<Axis>(), Err(ParseAxisError::UnknownName));
+	}
+
+	#[test]
+	fn axis_usages_round_trip() {
+		for axis in Axis::all() {
+			assert_eq!(Axis::from_usage(axis.usage()), Some(axis));
+		}
+
+		assert_eq!(Axis::from_usage(vjoy_sys::HID_USAGE_SL0), Some(Axis::Slider));
+		assert_eq!(Axis::from_usage(vjoy_sys::HID_USAGE_SL1), Some(Axis::Dial));
+		assert_eq!(Axis::from_usage(0), None);
+	}
+
+	#[test]
+	fn pov_direction_from_centidegrees() {
+		let cases = [
+			(0, PovDirection::North),
+			(4499, PovDirection::North),
+			(4500, PovDirection::East),
+			(13499, PovDirection::East),
+			(13500, PovDirection::South),
+			(22499, PovDirection::South),
+			(22500, PovDirection::West),
+			(31499, PovDirection::West),
+			(31500, PovDirection::North),
+			(35999, PovDirection::North),
+			(36000, PovDirection::North),
+			(40499, PovDirection::North),
+			(40500, PovDirection::East),
+			(u16::MAX, PovDirection::West),
+		];
+
+		for (value, expected) in cases {
+			assert_eq!(PovDirection::from_centidegrees(value), expected, "{}", value);
+		}
+	}
+
+	#[test]
+	fn device_id_index_round_trips() {
+		for i in 0..usize::from(u8::MAX) {
+			assert_eq!(DeviceId::from_index(i).unwrap().to_index(), i);
+		}
+
+		assert_eq!(DeviceId::from_index(u8::MAX.into()), Err(DeviceIdFromIndexError::TooLarge));
+	}
+
+	#[test]
+	fn axis_values_round_trip() {
+		for range in [0..=32767, -16384..=16383, 1..=1000, -1..=0] {
+			for raw in range.clone() {
+				let value = normalize_axis_value(range.clone(), raw).unwrap();
+				assert!((0.0..=1.0).contains(&value));
+				assert_eq!(denormalize_axis_value(range.clone(), value), Ok(raw), "{:?}, {}", range, raw);
+			}
+
+			assert_eq!(normalize_axis_value(range.clone(), *range.start()), Ok(0.0));
+			assert_eq!(normalize_axis_value(range.clone(), *range.end()), Ok(1.0));
+			assert_eq!(normalize_axis_value(range.clone(), *range.end() + 1), Err(GetAxisError::Value));
+			assert_eq!(denormalize_axis_value(range.clone(), 1.5), Err(SetAxisError::Value));
+		}
+
+		assert_eq!(normalize_axis_value(5..=5, 5), Err(GetAxisError::GetRange(AxisRangeError::Degenerate)));
+	}
+
+	#[test]
+	fn device_id_from_str() {
+		assert_eq!("0".parse::<DeviceId>(), Err(ParseDeviceIdError::Zero));
+		assert_eq!("1".parse::<DeviceId>(), Ok(DeviceId::from_raw(1).unwrap()));
+		assert_eq!("255".parse::<DeviceId>(), Ok(DeviceId::from_raw(255).unwrap()));
+		assert_eq!("256".parse::<DeviceId>(), Err(ParseDeviceIdError::TooLarge));
+
+		for s in ["", "one", "-1", "1.0", " 1"] {
+			assert_eq!(s.parse::<DeviceId>(), Err(ParseDeviceIdError::Invalid), "{:?}", s);
+		}
+	}
+
+	#[cfg(feature = "mock")]
+	#[test]
+	fn set_buttons_stops_at_invalid_index() {
+		let (_vjoy, device) = test_slot();
+
+		let result = device.set_buttons([(0, true), (3, true), (MAX_BUTTONS + 5, true), (5, true)]);
+		let error = result.unwrap_err();
+		assert_eq!(error.index(), MAX_BUTTONS + 5);
+		assert_eq!(error.error(), SetButtonError::NoSuchButton);
+
+		assert_eq!(device.get_button(0), Some(true));
+		assert_eq!(device.get_button(3), Some(true));
+		assert_eq!(device.get_button(5), Some(false));
+
+		assert!(device.set_buttons([(1, true), (2, true)]).is_ok());
+		assert_eq!(device.get_button(2), Some(true));
+	}
+
+	#[cfg(feature = "mock")]
+	#[test]
+	fn reentrant_setters_are_kept() {
+		let (_vjoy, device) = test_slot();
+
+		device.with_state(|state| {
+			state.set_button(0, true).unwrap();
+			state.set_axis(Axis::Y, 100);
+			state.set_axis(Axis::Z, 200);
+
+			device.set_button(1, true).unwrap();
+			device.set_axis_raw(Axis::X, 1234).unwrap();
+			device.set_axis_raw(Axis::Z, 300).unwrap();
+			device.set_cont_pov(1, Some(9000)).unwrap();
+			device.with_state(|inner| inner.set_button(2, true).unwrap());
+		});
+
+		assert_eq!(device.buttons_mask(), 0b111);
+		assert_eq!(device.get_axis_raw(Axis::X), 1234);
+		assert_eq!(device.get_axis_raw(Axis::Y), 100);
+		assert_eq!(device.get_cont_pov(1), Some(Some(9000)));
+
+		// Where both change the same input, the closure's value wins.
+		assert_eq!(device.get_axis_raw(Axis::Z), 200);
+
+		device.apply().unwrap();
+		assert_eq!(crate::mock::applied_state(device.id()).unwrap().buttons_mask(), 0b111);
+	}
+
+	#[cfg(feature = "mock")]
+	#[test]
+	fn reacquire_restores_state() {
+		let (_vjoy, device) = test_slot();
+		let id = device.id();
+
+		device.set_button(3, true).unwrap();
+		device.set_axis_raw(Axis::Y, 1234).unwrap();
+
+		let device = device.reacquire().unwrap();
+		assert_eq!(device.status(), Status::Acquired);
+		assert_eq!(device.get_button(3), Some(true));
+		assert_eq!(device.get_axis_raw(Axis::Y), 1234);
+
+		let applied = crate::mock::applied_state(id).unwrap();
+		assert_eq!(applied.button(3), Some(true));
+		assert_eq!(applied.axis(Axis::Y), 1234);
+	}
+
+	#[cfg(feature = "mock")]
+	#[test]
+	fn restored_state_is_applied_to_target() {
+		let vjoy = TestInterface::new([test_device(), test_device()]);
+		let first = vjoy.acquire(1);
+		let second = vjoy.acquire(2);
+
+		first.set_button(4, true).unwrap();
+		first.set_axis_raw(Axis::Z, 4321).unwrap();
+
+		second.restore(&first.snapshot());
+		second.apply().unwrap();
+
+		assert!(crate::mock::applied_state(first.id()).is_none());
+
+		let mut applied = crate::mock::applied_state(second.id()).unwrap();
+		assert_eq!(applied.raw_mut().bDevice, second.id().to_raw());
+		assert_eq!(applied.button(4), Some(true));
+		assert_eq!(applied.axis(Axis::Z), 4321);
+	}
+
+	#[cfg(all(feature = "mock", feature = "strict"))]
+	#[test]
+	fn strict_drops_unconfigured_buttons() {
+		let (_vjoy, device) = test_slot();
+
+		assert_eq!(device.set_button(15, true), Ok(()));
+		assert_eq!(device.set_button(16, true), Ok(()));
+		assert_eq!(device.set_buttons([(14, true), (20, true)]), Ok(()));
+		assert_eq!(device.set_button(MAX_BUTTONS, true), Err(SetButtonError::NoSuchButton));
+
+		let state = device.snapshot();
+		assert_eq!(state.button(14), Some(true));
+		assert_eq!(state.button(15), Some(true));
+		assert_eq!(state.button(16), Some(false));
+		assert_eq!(state.button(20), Some(false));
+	}
+
+	#[cfg(all(feature = "mock", feature = "strict"))]
+	#[test]
+	fn strict_drops_unconfigured_momentary_buttons() {
+		let (_vjoy, device) = test_slot();
+
+		assert_eq!(device.set_button_momentary(20), Ok(()));
+		assert_eq!(device.momentary.get(), 0);
+
+		assert_eq!(device.set_button_momentary(15), Ok(()));
+		assert_eq!(device.momentary.get(), 1 << 15);
+		assert_eq!(device.snapshot().button(20), Some(false));
+	}
+
+	#[cfg(feature = "mock")]
+	#[test]
+	fn apply_checked_falls_back_to_neutral() {
+		let (vjoy, device) = test_slot();
+
+		device.set_button(2, true).unwrap();
+		device.set_axis_raw(Axis::X, 0).unwrap();
+		device.set_cont_pov(0, Some(9000)).unwrap();
+		assert_eq!(device.apply_checked(), Ok(()));
+
+		// Relinquishing the device behind its back makes `UpdateVJD` fail.
+		vjoy.relinquish(device.id()).unwrap();
+		device.set_button(3, true).unwrap();
+
+		assert_eq!(device.apply_checked(), Err(ApplyError::Failed));
+
+		let state = device.snapshot();
+		assert_eq!(state.button(2), Some(false));
+		assert_eq!(state.button(3), Some(false));
+		assert_eq!(state.axis(Axis::X), axis_center(&(0..=vjoy_sys::VJOY_AXIS_MAX_VALUE as i32)));
+		assert_eq!(state.cont_pov(0), Some(None));
+
+		// Only the state applied before the failure reached the device.
+		let applied = crate::mock::applied_state(device.id()).unwrap();
+		assert_eq!(applied.button(2), Some(true));
+		assert_eq!(applied.button(3), Some(false));
+	}
+
+	#[cfg(feature = "mock")]
+	#[test]
+	fn set_all_axes_bipolar_lengths() {
+		let (_vjoy, device) = test_slot();
+		let max = vjoy_sys::VJOY_AXIS_MAX_VALUE as i32;
+
+		device.set_axis_raw(Axis::Z, 1234).unwrap();
+		assert_eq!(device.set_all_axes_bipolar(&[-1.0, 1.0]), Ok(()));
+		assert_eq!(device.get_axis_raw(Axis::X), 0);
+		assert_eq!(device.get_axis_raw(Axis::Y), max);
+		assert_eq!(device.get_axis_raw(Axis::Z), 1234);
+
+		assert_eq!(device.set_all_axes_bipolar(&[1.0; AXIS_COUNT]), Ok(()));
+		assert_eq!(device.axis_raw_values()[..3], [max; 3]);
+		assert!(device.axis_raw_values()[3..].iter().all(|&v| v == 0));
+
+		assert_eq!(device.set_all_axes_bipolar(&[-1.0; AXIS_COUNT + 1]), Err(SetAxisError::Length));
+		assert_eq!(device.axis_raw_values()[..3], [max; 3]);
+
+		assert_eq!(device.set_all_axes_bipolar(&[-1.0, -1.0, 2.0]), Err(SetAxisError::Value));
+		assert_eq!(device.axis_raw_values()[..3], [max; 3]);
+
+		// The test device lacks the fourth axis, but its value must still be valid.
+		for bad in [f32::NAN, 1.5, -1.0001] {
+			assert_eq!(device.set_all_axes_bipolar(&[-1.0, -1.0, -1.0, bad]), Err(SetAxisError::Value), "{}", bad);
+			assert_eq!(device.axis_raw_values()[..3], [max; 3]);
+		}
+	}
+
+	#[cfg(feature = "mock")]
+	#[test]
+	fn axis_setters_map_values() {
+		let (_vjoy, device) = test_slot();
+		let max = vjoy_sys::VJOY_AXIS_MAX_VALUE as i32;
+		let center = axis_center(&(0..=max));
+		let tolerance = AXIS_F32_TOLERANCE;
+
+		type Setter<'a> = &'a dyn Fn(&OwnedDeviceSlot) -> Result<(), SetAxisError>;
+		let cases: [(&str, Setter, Result<i32, SetAxisError>); 33] = [
+			("f32 above tolerated", &|d| d.set_axis_f32(Axis::X, 1.0 + tolerance / 2.0), Ok(max)),
+			("f32 below tolerated", &|d| d.set_axis_f32(Axis::X, -tolerance / 2.0), Ok(0)),
+			("f32 above rejected", &|d| d.set_axis_f32(Axis::X, 1.0 + tolerance * 2.0), Err(SetAxisError::Value)),
+			("f32 below rejected", &|d| d.set_axis_f32(Axis::X, -tolerance * 2.0), Err(SetAxisError::Value)),
+			("strict above", &|d| d.set_axis_f32_strict(Axis::X, 1.0 + tolerance / 2.0), Err(SetAxisError::Value)),
+			("strict below", &|d| d.set_axis_f32_strict(Axis::X, -tolerance / 2.0), Err(SetAxisError::Value)),
+
+			("deadzone center", &|d| d.set_axis_f32_deadzone(Axis::X, 0.5, 0.5), Ok(center)),
+			("deadzone upper edge", &|d| d.set_axis_f32_deadzone(Axis::X, 0.75, 0.5), Ok(center)),
+			("deadzone lower edge", &|d| d.set_axis_f32_deadzone(Axis::X, 0.25, 0.5), Ok(center)),
+			("deadzone above", &|d| d.set_axis_f32_deadzone(Axis::X, 0.875, 0.5), Ok(center + 8192)),
+			("deadzone below", &|d| d.set_axis_f32_deadzone(Axis::X, 0.125, 0.5), Ok(center - 8192)),
+			("deadzone max", &|d| d.set_axis_f32_deadzone(Axis::X, 1.0, 0.5), Ok(max)),
+			("deadzone min", &|d| d.set_axis_f32_deadzone(Axis::X, 0.0, 0.5), Ok(0)),
+			("deadzone none", &|d| d.set_axis_f32_deadzone(Axis::X, 1.0, 0.0), Ok(max)),
+			("deadzone negative", &|d| d.set_axis_f32_deadzone(Axis::X, 0.5, -0.1), Err(SetAxisError::Deadzone)),
+			("deadzone full", &|d| d.set_axis_f32_deadzone(Axis::X, 0.5, 1.0), Err(SetAxisError::Deadzone)),
+			("deadzone NaN", &|d| d.set_axis_f32_deadzone(Axis::X, 0.5, f32::NAN), Err(SetAxisError::Deadzone)),
+			("deadzone value", &|d| d.set_axis_f32_deadzone(Axis::X, 1.5, 0.5), Err(SetAxisError::Value)),
+
+			("no detents", &|d| d.set_axis_detented(Axis::X, 0.5, 0), Err(SetAxisError::Detents)),
+			("1 detent low", &|d| d.set_axis_detented(Axis::X, 0.0, 1), Ok(center)),
+			("1 detent high", &|d| d.set_axis_detented(Axis::X, 1.0, 1), Ok(center)),
+			("2 detents below half", &|d| d.set_axis_detented(Axis::X, 0.49, 2), Ok(0)),
+			("2 detents at half", &|d| d.set_axis_detented(Axis::X, 0.5, 2), Ok(max)),
+			("3 detents below quarter", &|d| d.set_axis_detented(Axis::X, 0.24, 3), Ok(0)),
+			("3 detents at quarter", &|d| d.set_axis_detented(Axis::X, 0.25, 3), Ok(center)),
+			("3 detents below 3 quarters", &|d| d.set_axis_detented(Axis::X, 0.74, 3), Ok(center)),
+			("3 detents at 3 quarters", &|d| d.set_axis_detented(Axis::X, 0.75, 3), Ok(max)),
+
+			("combined idle", &|d| d.set_combined_axis(Axis::X, 0.0, 0.0), Ok(center)),
+			("combined both", &|d| d.set_combined_axis(Axis::X, 1.0, 1.0), Ok(center)),
+			("combined throttle", &|d| d.set_combined_axis(Axis::X, 1.0, 0.0), Ok(max)),
+			("combined brake", &|d| d.set_combined_axis(Axis::X, 0.0, 1.0), Ok(0)),
+			("combined clamped", &|d| d.set_combined_axis(Axis::X, 2.0, -1.0), Ok(max)),
+			("combined NaN", &|d| d.set_combined_axis(Axis::X, f32::NAN, 0.0), Err(SetAxisError::Value)),
+		];
+
+		// A failed setter must leave the axis as it was.
+		for (name, set, expected) in cases {
+			device.set_axis_raw(Axis::X, 1234).unwrap();
+			assert_eq!(set(&device).map(|()| device.get_axis_raw(Axis::X)), expected, "{}", name);
+			if expected.is_err() {
+				assert_eq!(device.get_axis_raw(Axis::X), 1234, "{}", name);
+			}
+		}
+	}
+
+	#[cfg(feature = "mock")]
+	#[test]
+	fn axis_raw_values_match_get_axis_raw() {
+		let (_vjoy, device) = test_slot();
+
+		device.with_state(|s| {
+			for (i, axis) in Axis::all().enumerate() {
+				s.set_axis(axis, 100 * i as i32 + 1);
+			}
+		});
+
+		let values = device.axis_raw_values();
+		for (axis, value) in Axis::all().zip(values) {
+			assert_eq!(device.get_axis_raw(axis), value, "{:?}", axis);
+		}
+
+		assert_eq!(values[AXIS_COUNT - 1], 100 * (AXIS_COUNT as i32 - 1) + 1);
+	}
+
+	#[cfg(all(feature = "mock", not(feature = "const-range")))]
+	#[test]
+	fn axis_ranges_are_cached() {
+		let vjoy = TestInterface::new([test_device()]);
+		let slot = vjoy.device_slot(1u8).unwrap().unwrap();
+		let device = vjoy.acquire(1);
+
+		// Each `DeviceSlot` query goes to the driver.
+		let before = crate::mock::axis_range_queries();
+		for _ in 0..3 {
+			slot.axis_range(Axis::X).unwrap();
+		}
+		assert_eq!(crate::mock::axis_range_queries() - before, 3);
+
+		// An `OwnedDeviceSlot` only queries each axis once.
+		let before = crate::mock::axis_range_queries();
+		for value in [0.0, 0.5, 1.0] {
+			device.axis_range(Axis::X).unwrap();
+			device.set_axis_f32(Axis::X, value).unwrap();
+			device.get_axis_f32(Axis::X).unwrap();
+		}
+		assert_eq!(crate::mock::axis_range_queries() - before, 1);
+
+		device.axis_range(Axis::Y).unwrap();
+		assert_eq!(crate::mock::axis_range_queries() - before, 2);
+	}
+
+	#[cfg(all(feature = "mock", not(feature = "const-range")))]
+	#[test]
+	fn custom_axis_range_clamps_and_normalizes() {
+		let vjoy = TestInterface::new([crate::mock::MockDevice {
+			axes: [(Axis::X, -100..=100)].into(),
+			..test_device()
+		}]);
+		let slot = vjoy.device_slot(1u8).unwrap().unwrap();
+
+		let cases = [
+			(i32::MIN, -100, 0.0),
+			(-101, -100, 0.0),
+			(-100, -100, 0.0),
+			(0, 0, 0.5),
+			(100, 100, 1.0),
+			(101, 100, 1.0),
+			(i32::MAX, 100, 1.0),
+		];
+
+		for (raw, clamped, normalized) in cases {
+			assert_eq!(slot.clamp_axis_raw(Axis::X, raw), Ok(clamped), "{}", raw);
+			assert_eq!(slot.normalize_axis(Axis::X, clamped), Ok(normalized), "{}", raw);
+			assert_eq!(slot.denormalize_axis(Axis::X, normalized), Ok(clamped), "{}", raw);
+		}
+
+		assert_eq!(slot.normalize_axis(Axis::X, 101), Err(GetAxisError::Value));
+		assert_eq!(slot.clamp_axis_raw(Axis::Y, 0), Err(AxisRangeError::MinFailure));
+	}
+
+	#[cfg(feature = "mock")]
+	#[test]
+	fn input_counts_failed_or_invalid() {
+		let vjoy = TestInterface::new([
+			test_device(),
+			crate::mock::MockDevice {
+				num_buttons: MAX_BUTTONS + 1,
+				num_cont_pov: MAX_POVS + 1,
+				num_disc_pov: MAX_POVS + 1,
+				..test_device()
+			},
+		]);
+
+		let valid = vjoy.device_slot(1u8).unwrap().unwrap();
+		assert_eq!(valid.num_buttons(), Ok(16));
+		assert_eq!(valid.num_cont_pov(), Ok(2));
+		assert_eq!(valid.num_disc_pov(), Ok(0));
+
+		// The simulated driver returns a count which is too large.
+		let invalid = vjoy.device_slot(2u8).unwrap().unwrap();
+		assert_eq!(invalid.num_buttons(), Err(NumButtonsError::Invalid));
+		assert_eq!(invalid.num_cont_pov(), Err(NumContPovError::Invalid));
+		assert_eq!(invalid.num_disc_pov(), Err(NumDiscPovError::Invalid));
+
+		// The simulated driver returns a negative count for a missing device.
+		let missing = vjoy.device_slot(3u8).unwrap().unwrap();
+		assert_eq!(missing.num_buttons(), Err(NumButtonsError::Failed));
+		assert_eq!(missing.num_cont_pov(), Err(NumContPovError::Failed));
+		assert_eq!(missing.num_disc_pov(), Err(NumDiscPovError::Failed));
+	}
+
+	#[cfg(feature = "mock")]
+	#[test]
+	fn same_device_compares_ids() {
+		let vjoy = TestInterface::new([test_device(), test_device()]);
+		let first = vjoy.device_slot(1u8).unwrap().unwrap();
+		let second = vjoy.device_slot(2u8).unwrap().unwrap();
+		let owned = vjoy.acquire(1);
+
+		assert!(first.same_device(&vjoy.device_slot(1u8).unwrap().unwrap()));
+		assert!(first.same_device(&owned));
+		assert!(!first.same_device(&second));
+		assert!(!second.same_device(&owned));
+	}
+
+	#[cfg(feature = "mock")]
+	#[test]
+	fn momentary_button_lasts_one_frame() {
+		let (_vjoy, device) = test_slot();
+		let applied = || crate::mock::applied_state(device.id()).unwrap();
+
+		device.set_button(4, true).unwrap();
+		device.set_button_momentary(5).unwrap();
+		assert_eq!(device.get_button(5), Some(true));
+
+		device.apply().unwrap();
+		assert_eq!(applied().button(5), Some(true));
+		assert_eq!(device.get_button(5), Some(false));
+
+		for _ in 0..2 {
+			device.apply().unwrap();
+			assert_eq!(applied().button(5), Some(false));
+			assert_eq!(applied().button(4), Some(true));
+		}
+	}
+
+	#[cfg(feature = "mock")]
+	#[test]
+	fn press_and_release_clears_button_on_failure() {
+		let (vjoy, device) = test_slot();
+
+		// Relinquishing the device behind its back makes `apply` fail.
+		vjoy.relinquish(device.id()).unwrap();
+
+		let result = device.press_and_release(2, Duration::ZERO);
+		assert_eq!(result, Err(PressAndReleaseError::Apply(ApplyError::Failed)));
+		assert_eq!(device.get_button(2), Some(false));
+	}
+}
@@ -0,0 +1,60 @@
+use std::ops::{Deref};
+use std::sync::mpsc::{Receiver};
+
+use crate::ffi;
+use crate::lock::{VJoyLock};
+use crate::subscription::{CallbackSlot, Subscription};
+
+/// A `DeviceChangeEvent` is sent when the vJoy driver is reconfigured or a device is added or
+/// removed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct DeviceChangeEvent {
+	/// Whether the change is a removal (`true`) or an arrival (`false`).
+	pub removed: bool,
+
+	/// Whether this is the first event of a group of events caused by the same change.
+	pub first: bool,
+}
+
+/// A `DeviceChanges` is a subscription to device change events, created by
+/// `Interface::device_change_events`. It dereferences to the `Receiver` that the events are sent to.
+///
+/// vJoy calls its removal callback on a thread of its own. Rather than run user code on that
+/// thread, the callback only forwards each event into a channel, which may then be read from any
+/// thread that holds the subscription (which, like `Interface`, is confined to the thread that owns
+/// the vJoy lock).
+///
+/// vJoy supports only a single removal callback, so creating a new subscription disconnects any
+/// previous one. The callback is unregistered when the current subscription is dropped.
+#[derive(Debug)]
+pub struct DeviceChanges(Subscription<DeviceChangeEvent>);
+
+impl DeviceChanges {
+	pub(crate) fn new(lock: VJoyLock) -> DeviceChanges {
+		let subscription = Subscription::new(&CURRENT, lock, || unsafe {
+			ffi::RegisterRemovalCB(None, std::ptr::null_mut());
+		});
+
+		unsafe { ffi::RegisterRemovalCB(Some(removal_callback), std::ptr::null_mut()); }
+		DeviceChanges(subscription)
+	}
+}
+
+impl Deref for DeviceChanges {
+	type Target = Receiver<DeviceChangeEvent>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+static CURRENT: CallbackSlot<DeviceChangeEvent> = CallbackSlot::new();
+
+unsafe extern "C" fn removal_callback(removed: vjoy_sys::BOOL, first: vjoy_sys::BOOL, _data: vjoy_sys::PVOID) {
+	let event = DeviceChangeEvent {
+		removed: removed != 0,
+		first: first != 0,
+	};
+
+	CURRENT.send(event);
+}
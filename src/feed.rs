@@ -0,0 +1,84 @@
+use std::ops::{ControlFlow};
+use std::time::{Duration};
+
+use tokio::sync::mpsc::{self, error::TryRecvError};
+use tokio::time::{self, MissedTickBehavior};
+
+use crate::{ApplyError, DeviceState, OwnedDeviceSlot};
+
+/// A `FeedLoop` applies a device's state at a fixed rate from an async task.
+///
+/// Since an `OwnedDeviceSlot` is confined to the thread that created its `Interface`, the futures
+/// returned by `FeedLoop` are not `Send`. They must be run on that same thread, e.g. with a
+/// current-thread runtime's `block_on` or within a `tokio::task::LocalSet`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FeedLoop {
+	interval: Duration,
+}
+
+impl FeedLoop {
+	/// Creates a loop which applies the state `rate_hz` times per second, or `None` if the rate is
+	/// zero or above 1 GHz (where the interval would round down to zero nanoseconds).
+	pub fn new(rate_hz: u32) -> Option<FeedLoop> {
+		if rate_hz == 0 {
+			return None;
+		}
+
+		FeedLoop::with_interval(Duration::from_secs(1) / rate_hz)
+	}
+
+	/// Creates a loop which applies the state once per `interval`, or `None` if the interval is zero.
+	pub fn with_interval(interval: Duration) -> Option<FeedLoop> {
+		(!interval.is_zero()).then_some(FeedLoop { interval })
+	}
+
+	/// The interval between applies.
+	pub fn interval(&self) -> Duration {
+		self.interval
+	}
+
+	/// Calls `update` on each tick to modify the device's buffered state, then applies it. The loop
+	/// ends when `update` returns `ControlFlow::Break`, or when applying fails.
+	///
+	/// If a tick is missed (e.g. because `update` took too long), it is skipped rather than being
+	/// made up with a burst of applies.
+	pub async fn run(&self, device: &OwnedDeviceSlot, mut update: impl FnMut(&OwnedDeviceSlot) -> ControlFlow<()>) -> Result<(), ApplyError> {
+		let mut ticks = time::interval(self.interval);
+		ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+		loop {
+			ticks.tick().await;
+
+			if update(device).is_break() {
+				return Ok(());
+			}
+
+			device.apply()?;
+		}
+	}
+
+	/// Applies the most recent state received from `states` on each tick. If no new state has been
+	/// received since the last tick, the previous one is applied again. The loop ends once the
+	/// sending half of the channel is closed and all states have been received, or when applying
+	/// fails.
+	pub async fn run_channel(&self, device: &OwnedDeviceSlot, mut states: mpsc::Receiver<DeviceState>) -> Result<(), ApplyError> {
+		self.run(device, |d| {
+			let mut latest = None;
+			let closed = loop {
+				match states.try_recv() {
+					Ok(state) => latest = Some(state),
+					Err(TryRecvError::Empty) => break false,
+					Err(TryRecvError::Disconnected) => break true,
+				}
+			};
+
+			match latest {
+				Some(state) => d.restore(&state),
+				None if closed => return ControlFlow::Break(()),
+				None => {},
+			}
+
+			ControlFlow::Continue(())
+		}).await
+	}
+}
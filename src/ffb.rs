@@ -0,0 +1,589 @@
+use std::ops::{Deref};
+use std::sync::{Mutex};
+use std::sync::mpsc::{Receiver};
+use std::time::{Instant};
+
+use crate::{DeviceId};
+use crate::device::{MAX_DEVICES};
+use crate::ffi;
+use crate::lock::{VJoyLock};
+use crate::subscription::{CallbackSlot, Subscription};
+
+/// An `FfbEffect` is a type of force-feedback effect which a vJoy device may support.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum FfbEffect {
+	Constant,
+	Ramp,
+	Square,
+	Sine,
+	Triangle,
+	SawtoothUp,
+	SawtoothDown,
+	Spring,
+	Damper,
+	Inertia,
+	Friction,
+	Custom,
+}
+
+impl FfbEffect {
+	// Converts one of the `vjoy_sys::FFBEType_ET_*` constants used by effect reports.
+	fn from_effect_type(effect_type: vjoy_sys::FFBEType) -> Option<FfbEffect> {
+		match effect_type {
+			vjoy_sys::FFBEType_ET_CONST => Some(FfbEffect::Constant),
+			vjoy_sys::FFBEType_ET_RAMP => Some(FfbEffect::Ramp),
+			vjoy_sys::FFBEType_ET_SQR => Some(FfbEffect::Square),
+			vjoy_sys::FFBEType_ET_SINE => Some(FfbEffect::Sine),
+			vjoy_sys::FFBEType_ET_TRNGL => Some(FfbEffect::Triangle),
+			vjoy_sys::FFBEType_ET_STUP => Some(FfbEffect::SawtoothUp),
+			vjoy_sys::FFBEType_ET_STDN => Some(FfbEffect::SawtoothDown),
+			vjoy_sys::FFBEType_ET_SPRNG => Some(FfbEffect::Spring),
+			vjoy_sys::FFBEType_ET_DMPR => Some(FfbEffect::Damper),
+			vjoy_sys::FFBEType_ET_INRT => Some(FfbEffect::Inertia),
+			vjoy_sys::FFBEType_ET_FRCTN => Some(FfbEffect::Friction),
+			vjoy_sys::FFBEType_ET_CSTM => Some(FfbEffect::Custom),
+			_ => None,
+		}
+	}
+
+	pub fn usage(&self) -> u32 {
+		match self {
+			FfbEffect::Constant => vjoy_sys::HID_USAGE_CONST,
+			FfbEffect::Ramp => vjoy_sys::HID_USAGE_RAMP,
+			FfbEffect::Square => vjoy_sys::HID_USAGE_SQUR,
+			FfbEffect::Sine => vjoy_sys::HID_USAGE_SINE,
+			FfbEffect::Triangle => vjoy_sys::HID_USAGE_TRNG,
+			FfbEffect::SawtoothUp => vjoy_sys::HID_USAGE_STUP,
+			FfbEffect::SawtoothDown => vjoy_sys::HID_USAGE_STDN,
+			FfbEffect::Spring => vjoy_sys::HID_USAGE_SPRNG,
+			FfbEffect::Damper => vjoy_sys::HID_USAGE_DMPR,
+			FfbEffect::Inertia => vjoy_sys::HID_USAGE_INRT,
+			FfbEffect::Friction => vjoy_sys::HID_USAGE_FRIC,
+			FfbEffect::Custom => vjoy_sys::HID_USAGE_CUSTM,
+		}
+	}
+}
+
+impl TryFrom<u32> for FfbEffect {
+	type Error = ();
+
+	fn try_from(usage: u32) -> Result<Self, Self::Error> {
+		match usage {
+			vjoy_sys::HID_USAGE_CONST => Ok(FfbEffect::Constant),
+			vjoy_sys::HID_USAGE_RAMP => Ok(FfbEffect::Ramp),
+			vjoy_sys::HID_USAGE_SQUR => Ok(FfbEffect::Square),
+			vjoy_sys::HID_USAGE_SINE => Ok(FfbEffect::Sine),
+			vjoy_sys::HID_USAGE_TRNG => Ok(FfbEffect::Triangle),
+			vjoy_sys::HID_USAGE_STUP => Ok(FfbEffect::SawtoothUp),
+			vjoy_sys::HID_USAGE_STDN => Ok(FfbEffect::SawtoothDown),
+			vjoy_sys::HID_USAGE_SPRNG => Ok(FfbEffect::Spring),
+			vjoy_sys::HID_USAGE_DMPR => Ok(FfbEffect::Damper),
+			vjoy_sys::HID_USAGE_INRT => Ok(FfbEffect::Inertia),
+			vjoy_sys::HID_USAGE_FRIC => Ok(FfbEffect::Friction),
+			vjoy_sys::HID_USAGE_CUSTM => Ok(FfbEffect::Custom),
+			_ => Err(()),
+		}
+	}
+}
+
+/// An `FfbPacket` is a force-feedback packet sent to a vJoy device by an application (e.g. a game),
+/// as received through `Interface::ffb_packets`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct FfbPacket {
+	device: Option<DeviceId>,
+	packet_type: Option<vjoy_sys::FFBPType>,
+
+	size: u32,
+	command: u32,
+	bytes: Vec<u8>,
+}
+
+impl FfbPacket {
+	unsafe fn from_raw(raw: *const vjoy_sys::FFB_DATA) -> FfbPacket {
+		let raw = &*raw;
+
+		// The size of a packet includes its 8-byte header (`size` and `cmd`).
+		let len = (raw.size as usize).saturating_sub(8);
+		let bytes = if raw.data.is_null() || len == 0 {
+			Vec::new()
+		} else {
+			std::slice::from_raw_parts(raw.data, len).to_vec()
+		};
+
+		// The size is recomputed from the bytes actually copied, so that it can never claim more data
+		// than the packet holds when it is passed to the parsers.
+		let mut packet = FfbPacket {
+			device: None,
+			packet_type: None,
+
+			size: (bytes.len() + 8) as u32,
+			command: raw.cmd,
+			bytes,
+		};
+
+		packet.device = packet.parse(ffi::Ffb_h_DeviceID)
+			.and_then(|id: i32| u8::try_from(id).ok())
+			.and_then(|id| DeviceId::from_raw(id).ok());
+		packet.packet_type = packet.parse(ffi::Ffb_h_Type);
+		packet
+	}
+
+	/// The raw bytes of the packet's data, not including its header.
+	pub fn bytes(&self) -> &[u8] {
+		&self.bytes
+	}
+
+	/// The raw command of the packet.
+	pub fn command(&self) -> u32 {
+		self.command
+	}
+
+	/// Decodes the packet as a condition effect report (e.g. spring or damper), if it is one.
+	pub fn condition_effect(&self) -> Option<FfbConditionEffect> {
+		let raw: vjoy_sys::FFB_EFF_COND = self.parse(ffi::Ffb_h_Eff_Cond)?;
+
+		Some(FfbConditionEffect {
+			block_index: raw.EffectBlockIndex,
+			is_y: raw.isY != 0,
+			center_point_offset: raw.CenterPointOffset,
+			positive_coefficient: raw.PosCoeff,
+			negative_coefficient: raw.NegCoeff,
+			positive_saturation: raw.PosSatur,
+			negative_saturation: raw.NegSatur,
+			dead_band: raw.DeadBand,
+		})
+	}
+
+	/// Decodes the packet as a constant force effect report, if it is one.
+	pub fn constant_effect(&self) -> Option<FfbConstantEffect> {
+		let raw: vjoy_sys::FFB_EFF_CONSTANT = self.parse(ffi::Ffb_h_Eff_Constant)?;
+
+		Some(FfbConstantEffect {
+			block_index: raw.EffectBlockIndex,
+			magnitude: raw.Magnitude,
+		})
+	}
+
+	/// The device that the packet was sent to, if it could be determined.
+	pub fn device(&self) -> Option<DeviceId> {
+		self.device
+	}
+
+	/// Decodes the packet as a device gain report, if it is one.
+	pub fn device_gain(&self) -> Option<u8> {
+		self.parse(ffi::Ffb_h_DevGain)
+	}
+
+	/// Decodes the packet as an effect operation (start, solo or stop), if it is one.
+	pub fn effect_operation(&self) -> Option<FfbEffectOperation> {
+		let raw: vjoy_sys::FFB_EFF_OP = self.parse(ffi::Ffb_h_EffOp)?;
+
+		let operation = match raw.EffectOp {
+			vjoy_sys::FFBOP_EFF_START => FfbOperation::Start,
+			vjoy_sys::FFBOP_EFF_SOLO => FfbOperation::Solo,
+			vjoy_sys::FFBOP_EFF_STOP => FfbOperation::Stop,
+			_ => return None,
+		};
+
+		Some(FfbEffectOperation {
+			block_index: raw.EffectBlockIndex,
+			operation,
+			loop_count: raw.LoopCount,
+		})
+	}
+
+	/// Decodes the packet as a set effect report, if it is one. The effect is `None` if vJoy reported
+	/// an effect type that `FfbEffect` does not know.
+	pub fn effect_report(&self) -> Option<FfbEffectReport> {
+		let raw: vjoy_sys::FFB_EFF_REPORT = self.parse(ffi::Ffb_h_Eff_Report)?;
+
+		// UNSAFE: Both fields of the union are bytes, so either may be read whichever was written.
+		let direction = if raw.Polar != 0 {
+			FfbDirection::Polar(unsafe { raw.__bindgen_anon_1.Direction })
+		} else {
+			FfbDirection::Cartesian(unsafe { raw.__bindgen_anon_1.DirX } as i8, raw.DirY as i8)
+		};
+
+		Some(FfbEffectReport {
+			block_index: raw.EffectBlockIndex,
+			effect: FfbEffect::from_effect_type(raw.EffectType),
+			duration: raw.Duration,
+			trigger_repeat: raw.TrigerRpt,
+			sample_period: raw.SamplePrd,
+			gain: raw.Gain,
+			trigger_button: raw.TrigerBtn,
+			direction,
+		})
+	}
+
+	/// Decodes the packet as an envelope report, if it is one.
+	pub fn envelope(&self) -> Option<FfbEnvelope> {
+		let raw: vjoy_sys::FFB_EFF_ENVLP = self.parse(ffi::Ffb_h_Eff_Envlp)?;
+
+		Some(FfbEnvelope {
+			block_index: raw.EffectBlockIndex,
+			attack_level: raw.AttackLevel,
+			fade_level: raw.FadeLevel,
+			attack_time: raw.AttackTime,
+			fade_time: raw.FadeTime,
+		})
+	}
+
+	/// The raw type of the packet (one of the `vjoy_sys::FFBPType_*` constants), if it could be
+	/// determined.
+	pub fn packet_type(&self) -> Option<vjoy_sys::FFBPType> {
+		self.packet_type
+	}
+
+	/// Decodes the packet as a periodic effect report (e.g. sine or square), if it is one.
+	pub fn periodic_effect(&self) -> Option<FfbPeriodicEffect> {
+		let raw: vjoy_sys::FFB_EFF_PERIOD = self.parse(ffi::Ffb_h_Eff_Period)?;
+
+		Some(FfbPeriodicEffect {
+			block_index: raw.EffectBlockIndex,
+			magnitude: raw.Magnitude,
+			offset: raw.Offset,
+			phase: raw.Phase,
+			period: raw.Period,
+		})
+	}
+
+	/// Decodes the packet as a ramp force effect report, if it is one.
+	pub fn ramp_effect(&self) -> Option<FfbRampEffect> {
+		let raw: vjoy_sys::FFB_EFF_RAMP = self.parse(ffi::Ffb_h_Eff_Ramp)?;
+
+		Some(FfbRampEffect {
+			block_index: raw.EffectBlockIndex,
+			start: raw.Start,
+			end: raw.End,
+		})
+	}
+
+	// Runs one of the `Ffb_h_*` parsers over this packet, returning its output if it succeeded.
+	fn parse<T>(&self, parser: unsafe extern "C" fn(*const vjoy_sys::FFB_DATA, *mut T) -> vjoy_sys::DWORD) -> Option<T> {
+		// A packet without any data does not even have a report ID, so there is nothing to parse.
+		if self.bytes.is_empty() {
+			return None;
+		}
+
+		// The parsers read the packet's data without checking its size, so they are given a copy that
+		// is zero-padded to cover whatever they may read. They are still passed the real size.
+		let mut data = self.bytes.clone();
+		data.resize(data.len().max(PARSE_LEN).max(1 + size_of::<T>()), 0);
+
+		let raw = vjoy_sys::FFB_DATA {
+			size: self.size,
+			cmd: self.command,
+			data: data.as_mut_ptr(),
+		};
+
+		// UNSAFE: The packet's data outlives the call, and all of the parsers' outputs are plain C
+		// structs or integers, for which zeroed memory is valid even if the parser did not write to it.
+		let mut out = std::mem::MaybeUninit::<T>::zeroed();
+		let result = unsafe { parser(&raw, out.as_mut_ptr()) };
+		(result == 0).then(|| unsafe { out.assume_init() })
+	}
+}
+
+/// A condition effect report, decoded from an `FfbPacket`. Each report covers a single axis.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FfbConditionEffect {
+	pub block_index: u8,
+	pub is_y: bool,
+	pub center_point_offset: i32,
+	pub positive_coefficient: i32,
+	pub negative_coefficient: i32,
+	pub positive_saturation: u32,
+	pub negative_saturation: u32,
+	pub dead_band: i32,
+}
+
+/// A constant force effect report, decoded from an `FfbPacket`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FfbConstantEffect {
+	pub block_index: u8,
+	pub magnitude: i32,
+}
+
+/// The direction of a force-feedback effect.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FfbDirection {
+	/// A polar direction, where 0x00..=0xFF covers 0..360 degrees.
+	Polar(u8),
+	/// A cartesian direction, as X (positive to the right) and Y (positive downwards).
+	Cartesian(i8, i8),
+}
+
+/// An effect operation, decoded from an `FfbPacket`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FfbEffectOperation {
+	pub block_index: u8,
+	pub operation: FfbOperation,
+	pub loop_count: u8,
+}
+
+/// A set effect report, decoded from an `FfbPacket`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FfbEffectReport {
+	pub block_index: u8,
+	pub effect: Option<FfbEffect>,
+	/// In milliseconds, where 0xFFFF is infinite.
+	pub duration: u16,
+	pub trigger_repeat: u16,
+	pub sample_period: u16,
+	pub gain: u8,
+	pub trigger_button: u8,
+	pub direction: FfbDirection,
+}
+
+/// An envelope report, decoded from an `FfbPacket`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FfbEnvelope {
+	pub block_index: u8,
+	pub attack_level: u32,
+	pub fade_level: u32,
+	pub attack_time: u32,
+	pub fade_time: u32,
+}
+
+/// An operation applied to an effect by an `FfbEffectOperation`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FfbOperation {
+	Start,
+	Solo,
+	Stop,
+}
+
+/// A periodic effect report, decoded from an `FfbPacket`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FfbPeriodicEffect {
+	pub block_index: u8,
+	pub magnitude: u32,
+	pub offset: i32,
+	pub phase: u32,
+	pub period: u32,
+}
+
+/// A ramp force effect report, decoded from an `FfbPacket`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FfbRampEffect {
+	pub block_index: u8,
+	pub start: i32,
+	pub end: i32,
+}
+
+/// An `FfbPackets` is a subscription to the force-feedback packets sent to vJoy devices, created by
+/// `Interface::ffb_packets`. It dereferences to the `Receiver` that the packets are sent to.
+///
+/// vJoy calls its force-feedback callback on a thread of its own, and that callback must not block.
+/// The callback therefore only copies each packet and forwards it into a channel, which is expected
+/// to be polled (e.g. with `try_iter`) by the thread that holds the subscription (which, like
+/// `Interface`, is confined to the thread that owns the vJoy lock).
+///
+/// vJoy supports only a single force-feedback callback, so creating a new subscription disconnects
+/// any previous one. The callback is unregistered when the current subscription is dropped.
+#[derive(Debug)]
+pub struct FfbPackets(Subscription<FfbPacket>);
+
+impl FfbPackets {
+	pub(crate) fn new(lock: VJoyLock) -> FfbPackets {
+		let subscription = Subscription::new(&CURRENT, lock, || unsafe {
+			ffi::FfbRegisterGenCB(None, std::ptr::null_mut());
+		});
+
+		unsafe { ffi::FfbRegisterGenCB(Some(ffb_callback), std::ptr::null_mut()); }
+		FfbPackets(subscription)
+	}
+}
+
+impl Deref for FfbPackets {
+	type Target = Receiver<FfbPacket>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+static CURRENT: CallbackSlot<FfbPacket> = CallbackSlot::new();
+
+// The length that packets are padded to before being parsed: a report ID followed by the largest of
+// the parsers' outputs, each of which is larger than the packed report that it is decoded from.
+const PARSE_LEN: usize = 1 + max_size(&[
+	size_of::<vjoy_sys::FFB_EFF_COND>(),
+	size_of::<vjoy_sys::FFB_EFF_CONSTANT>(),
+	size_of::<vjoy_sys::FFB_EFF_ENVLP>(),
+	size_of::<vjoy_sys::FFB_EFF_OP>(),
+	size_of::<vjoy_sys::FFB_EFF_PERIOD>(),
+	size_of::<vjoy_sys::FFB_EFF_RAMP>(),
+	size_of::<vjoy_sys::FFB_EFF_REPORT>(),
+]);
+
+const fn max_size(sizes: &[usize]) -> usize {
+	let mut max = 0;
+
+	let mut i = 0;
+	while i < sizes.len() {
+		if sizes[i] > max {
+			max = sizes[i];
+		}
+		i += 1;
+	}
+
+	max
+}
+
+// When each device (indexed by `DeviceId::to_index`) last had a packet sent to it.
+static LAST_PACKET_AT: Mutex<[Option<Instant>; MAX_DEVICES]> = Mutex::new([None; MAX_DEVICES]);
+
+/// Returns when a packet was last sent to the given device, if one has been received.
+pub(crate) fn last_packet_at(device: DeviceId) -> Option<Instant> {
+	let last = LAST_PACKET_AT.lock().unwrap();
+	last.get(device.to_index()).copied().flatten()
+}
+
+unsafe extern "C" fn ffb_callback(data: vjoy_sys::PVOID, _user: vjoy_sys::PVOID) {
+	if data.is_null() {
+		return;
+	}
+
+	let now = Instant::now();
+
+	// This decodes the packet's device and type using vJoy's `Ffb_h_*` parsers on vJoy's own thread.
+	// Unlike the rest of the vJoy interface, those parsers are pure functions of the packet passed
+	// to them (they touch no global state), so they are exempt from the lock's single-thread rule.
+	let packet = FfbPacket::from_raw(data as *const vjoy_sys::FFB_DATA);
+
+	if let (Some(device), Ok(mut last)) = (packet.device(), LAST_PACKET_AT.lock()) {
+		if let Some(at) = last.get_mut(device.to_index()) {
+			*at = Some(now);
+		}
+	}
+
+	CURRENT.send(packet);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn effect_types_map_to_effects() {
+		assert_eq!(FfbEffect::from_effect_type(vjoy_sys::FFBEType_ET_NONE), None);
+		assert_eq!(FfbEffect::from_effect_type(vjoy_sys::FFBEType_ET_CONST), Some(FfbEffect::Constant));
+		assert_eq!(FfbEffect::from_effect_type(vjoy_sys::FFBEType_ET_CSTM), Some(FfbEffect::Custom));
+
+		let effects = (vjoy_sys::FFBEType_ET_CONST..=vjoy_sys::FFBEType_ET_CSTM)
+			.filter_map(FfbEffect::from_effect_type)
+			.collect::<std::collections::BTreeSet<_>>();
+		assert_eq!(effects.len(), 12);
+	}
+
+	#[test]
+	fn packet_without_data_is_not_parsed() {
+		let raw = vjoy_sys::FFB_DATA {
+			size: 64,
+			cmd: 0,
+			data: std::ptr::null_mut(),
+		};
+
+		let packet = unsafe { FfbPacket::from_raw(&raw) };
+		assert!(packet.bytes().is_empty());
+		assert_eq!(packet.size, 8);
+		assert_eq!(packet.device(), None);
+		assert_eq!(packet.constant_effect(), None);
+		assert_eq!(packet.effect_report(), None);
+		assert_eq!(packet.device_gain(), None);
+	}
+
+	#[test]
+	fn packet_size_matches_copied_data() {
+		let mut data = [0x12u8, 0x34, 0x56];
+		let raw = vjoy_sys::FFB_DATA {
+			size: 8 + data.len() as u32,
+			cmd: 0,
+			data: data.as_mut_ptr(),
+		};
+
+		let packet = unsafe { FfbPacket::from_raw(&raw) };
+		assert_eq!(packet.bytes(), &data);
+		assert_eq!(packet.size, 8 + data.len() as u32);
+	}
+
+	// A packet for device 1 holding the given data after its report ID, much shorter than any of the
+	// reports it may be decoded as.
+	fn short_packet(data: &[u8]) -> FfbPacket {
+		let bytes = [&[0x11u8][..], data].concat();
+
+		FfbPacket {
+			device: None,
+			packet_type: None,
+
+			size: 8 + bytes.len() as u32,
+			command: 0,
+			bytes,
+		}
+	}
+
+	#[test]
+	fn parsers_are_given_zero_padded_data() {
+		// Like vJoy's parsers, this reads the packet's data without checking its size.
+		unsafe extern "C" fn read_all(packet: *const vjoy_sys::FFB_DATA, out: *mut [u8; PARSE_LEN]) -> vjoy_sys::DWORD {
+			assert_eq!((*packet).size, 8 + 2);
+			std::ptr::copy_nonoverlapping((*packet).data, out as *mut u8, PARSE_LEN);
+			0
+		}
+
+		let data = short_packet(&[3]).parse(read_all).unwrap();
+		assert_eq!(data[..2], [0x11, 3]);
+		assert!(data[2..].iter().all(|&b| b == 0));
+	}
+
+	#[cfg(feature = "mock")]
+	#[test]
+	fn short_packets_are_decoded() {
+		type Decoder<'a> = &'a dyn Fn(&FfbPacket) -> Option<u8>;
+		let stop = vjoy_sys::FFBOP_EFF_STOP as u8;
+
+		// The operation follows the block index at offset 4 of `FFB_EFF_OP`, and 0 is not an operation.
+		let cases: [(&str, &[u8], Decoder, Option<u8>); 9] = [
+			("condition", &[3], &|p| p.condition_effect().map(|e| e.block_index), Some(3)),
+			("constant", &[3], &|p| p.constant_effect().map(|e| e.block_index), Some(3)),
+			("device gain", &[3], &|p| p.device_gain(), Some(3)),
+			("operation", &[3, 0, 0, 0, stop], &|p| p.effect_operation().map(|o| o.block_index), Some(3)),
+			("operation missing", &[3], &|p| p.effect_operation().map(|o| o.block_index), None),
+			("effect report", &[3], &|p| p.effect_report().map(|e| e.block_index), Some(3)),
+			("envelope", &[3], &|p| p.envelope().map(|e| e.block_index), Some(3)),
+			("periodic", &[3], &|p| p.periodic_effect().map(|e| e.block_index), Some(3)),
+			("ramp", &[3], &|p| p.ramp_effect().map(|e| e.block_index), Some(3)),
+		];
+
+		for (name, data, decode, expected) in cases {
+			assert_eq!(decode(&short_packet(data)), expected, "{}", name);
+		}
+	}
+
+	#[cfg(feature = "mock")]
+	#[test]
+	fn callback_records_packet_time() {
+		use crate::mock::{TestInterface, test_device};
+
+		let vjoy = TestInterface::new([test_device(), test_device()]);
+		let device = vjoy.acquire(2);
+		let packets = vjoy.ffb_packets();
+
+		// A report ID of 0x21 is report 1 for device 2.
+		let mut data = [0x21u8, 0x00];
+		let mut raw = vjoy_sys::FFB_DATA {
+			size: 8 + data.len() as u32,
+			cmd: 0,
+			data: data.as_mut_ptr(),
+		};
+
+		let before = Instant::now();
+		unsafe { ffb_callback(&mut raw as *mut _ as vjoy_sys::PVOID, std::ptr::null_mut()); }
+
+		let packet = packets.try_recv().unwrap();
+		assert_eq!(packet.device(), Some(device.id()));
+		assert!(device.last_ffb_at().is_some_and(|at| at >= before));
+	}
+}
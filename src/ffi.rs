@@ -0,0 +1,74 @@
+// All calls into the vJoy interface library are made through this module, so that they may be
+// routed to the simulated driver in `mock` instead when the `mock` feature is enabled, and so that
+// they may be logged when the `tracing` feature is enabled.
+
+#[cfg(feature = "tracing")]
+use std::os::raw::{c_int};
+
+#[cfg(feature = "tracing")]
+use vjoy_sys::{BOOL, FfbGenCB, LONG, PVOID, RemovalCB, UINT, VjdStat, WORD};
+
+mod backend {
+	#[cfg(not(feature = "mock"))]
+	pub(crate) use vjoy_sys::*;
+
+	#[cfg(feature = "mock")]
+	pub(crate) use crate::mock::ffi::*;
+}
+
+// The packet parsers are passed around as function pointers, so they are never wrapped. They only
+// read the packet passed to them, so they may also be called from threads without the lock.
+pub(crate) use backend::{
+	Ffb_h_DevGain, Ffb_h_DeviceID, Ffb_h_EffOp, Ffb_h_Eff_Cond, Ffb_h_Eff_Constant, Ffb_h_Eff_Envlp,
+	Ffb_h_Eff_Period, Ffb_h_Eff_Ramp, Ffb_h_Eff_Report, Ffb_h_Type,
+};
+
+// Without the `tracing` feature, each function is simply re-exported from the backend. With it,
+// each is wrapped in a function which logs its arguments and return value. Some functions go unused
+// depending on which features are enabled (e.g. `const-slots`).
+macro_rules! ffi_fns {
+	($(fn $name:ident($($arg:ident: $ty:ty),*) $(-> $ret:ty)?;)*) => {
+		$(
+			#[cfg(not(feature = "tracing"))]
+			#[allow(unused_imports)]
+			pub(crate) use backend::$name;
+
+			#[cfg(feature = "tracing")]
+			#[allow(dead_code, non_snake_case)]
+			pub(crate) unsafe fn $name($($arg: $ty),*) $(-> $ret)? {
+				let result = backend::$name($($arg),*);
+				tracing::debug!(function = stringify!($name), $($arg = ?$arg,)* ?result, "vJoy call");
+				result
+			}
+		)*
+	};
+}
+
+ffi_fns! {
+	fn AcquireVJD(rID: UINT) -> BOOL;
+	fn DriverMatch(DllVer: *mut WORD, DrvVer: *mut WORD) -> BOOL;
+	fn FfbRegisterGenCB(cb: FfbGenCB, data: PVOID);
+	fn GetNumberExistingVJD(n: *mut c_int) -> BOOL;
+	fn GetOwnerPid(rID: UINT) -> c_int;
+	fn GetVJDAxisMax(rID: UINT, Axis: UINT, Max: *mut LONG) -> BOOL;
+	fn GetVJDAxisMin(rID: UINT, Axis: UINT, Min: *mut LONG) -> BOOL;
+	fn GetVJDButtonNumber(rID: UINT) -> c_int;
+	fn GetVJDContPovNumber(rID: UINT) -> c_int;
+	fn GetVJDDiscPovNumber(rID: UINT) -> c_int;
+	fn GetVJDStatus(rID: UINT) -> VjdStat;
+	fn GetvJoyManufacturerString() -> PVOID;
+	fn GetvJoyMaxDevices(n: *mut c_int) -> BOOL;
+	fn GetvJoyProductString() -> PVOID;
+	fn GetvJoySerialNumberString() -> PVOID;
+	fn IsDeviceFfb(rID: UINT) -> BOOL;
+	fn IsDeviceFfbEffect(rID: UINT, Effect: UINT) -> BOOL;
+	fn RegisterRemovalCB(cb: RemovalCB, data: PVOID);
+	fn RelinquishVJD(rID: UINT);
+	fn ResetAll();
+	fn ResetButtons(rID: UINT) -> BOOL;
+	fn ResetPovs(rID: UINT) -> BOOL;
+	fn ResetVJD(rID: UINT) -> BOOL;
+	fn UpdateVJD(rID: UINT, pData: PVOID) -> BOOL;
+	fn isVJDExists(rID: UINT) -> BOOL;
+	fn vJoyEnabled() -> BOOL;
+}
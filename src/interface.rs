@@ -1,52 +1,122 @@
+#[cfg(not(feature = "const-slots"))]
+use std::cell::{OnceCell};
 use std::string::{FromUtf16Error};
+use std::thread::{self};
 
-use crate::{DeviceId, DeviceSlot, TryIntoDeviceIdError, Version, Versions, VJoyLock, util, VersionError};
+use crate::device;
+use crate::{AcquireError, DeviceChanges, DeviceId, DeviceReport, FfbPackets, DeviceSlot, LockOwner, OwnedDeviceSlot, Requirements, ScanReport, Status, TryIntoDeviceIdError, Version, Versions, VJoyLock, ffi, util, VersionError};
 
 #[derive(Clone, Debug)]
 pub struct Interface {
     lock: VJoyLock,
+    standard_range: bool,
+    #[cfg(not(feature = "const-slots"))]
+    num_slots: OnceCell<usize>,
 }
 
 impl Interface {
     // TODO: Error type?
     pub fn new() -> Result<Interface, NewInterfaceError> {
+        InterfaceBuilder::new().build()
+    }
+
+    /// Creates a builder for configuring an `Interface` before creating it.
+    pub fn builder() -> InterfaceBuilder {
+        InterfaceBuilder::new()
+    }
+
+    fn with_options(options: InterfaceBuilder) -> Result<Interface, NewInterfaceError> {
         let lock = VJoyLock::new()
-            .ok_or(NewInterfaceError::Locked)?;
+            .map_err(|owner| match owner {
+                Some(owner) if owner.thread_id() != thread::current().id() => NewInterfaceError::WrongThread { owner },
+                _ => NewInterfaceError::Locked,
+            })?;
 
-        let enabled = unsafe { vjoy_sys::vJoyEnabled() } != 0;
-        enabled.then_some(Interface { lock })
+        let enabled = unsafe { ffi::vJoyEnabled() } != 0;
+        enabled.then_some(Interface::from_lock(lock, options.standard_range))
             .ok_or(NewInterfaceError::NotAvailable)
     }
 
-    pub(crate) fn from_lock(lock: VJoyLock) -> Interface {
-        Interface { lock }
+    pub(crate) fn from_lock(lock: VJoyLock, standard_range: bool) -> Interface {
+        Interface {
+            lock,
+            standard_range,
+            #[cfg(not(feature = "const-slots"))]
+            num_slots: OnceCell::new(),
+        }
+    }
+
+    /// Acquires the first free device. If every free device fails to be acquired, the reason for the
+    /// last failure is returned.
+    pub fn acquire_any(&self) -> Result<OwnedDeviceSlot, AcquireAnyError> {
+        let mut last_error = None;
+        for slot in self.free_slots()? {
+            match slot.try_acquire() {
+                Ok(owned) => return Ok(owned),
+                Err((_, e)) => last_error = Some(e),
+            }
+        }
+
+        Err(last_error.map_or(AcquireAnyError::NoneFree, AcquireAnyError::Acquire))
+    }
+
+    /// Acquires the first free device which satisfies the given requirements. If every matching
+    /// device fails to be acquired, the reason for the last failure is returned.
+    pub fn acquire_matching(&self, req: &Requirements) -> Result<OwnedDeviceSlot, AcquireMatchingError> {
+        let mut last_error = None;
+        for slot in self.free_slots()?.filter(|d| req.matches(d)) {
+            match slot.try_acquire() {
+                Ok(owned) => return Ok(owned),
+                Err((_, e)) => last_error = Some(e),
+            }
+        }
+
+        Err(last_error.map_or(AcquireMatchingError::NoMatch, AcquireMatchingError::Acquire))
+    }
+
+    /// Counts the present devices, broken down by status, in a single pass over all device slots.
+    pub fn device_counts(&self) -> Result<DeviceCounts, DeviceSlotsError> {
+        let summary = StatusSummary::count(self.present_devices()?);
+
+        Ok(DeviceCounts {
+            present: summary.total(),
+            free: summary.free,
+            owned_by_us: summary.acquired,
+            busy: summary.busy,
+        })
+    }
+
+    /// Subscribes to events sent when the vJoy driver is reconfigured or devices are added or
+    /// removed. See `DeviceChanges` for details on the threading model.
+    pub fn device_change_events(&self) -> DeviceChanges {
+        DeviceChanges::new(self.lock.clone())
     }
 
     pub fn device_manufacturer(&self) -> Result<String, FromUtf16Error> {
         unsafe {
-            let ptr = vjoy_sys::GetvJoyManufacturerString();
+            let ptr = ffi::GetvJoyManufacturerString();
             util::decode_utf16(ptr as *const _)
         }
     }
 
     pub fn device_product(&self) -> Result<String, FromUtf16Error> {
         unsafe {
-            let ptr = vjoy_sys::GetvJoyProductString();
+            let ptr = ffi::GetvJoyProductString();
             util::decode_utf16(ptr as *const _)
         }
     }
 
     pub fn device_serial(&self) -> Result<String, FromUtf16Error> {
         unsafe {
-            let ptr = vjoy_sys::GetvJoySerialNumberString();
+            let ptr = ffi::GetvJoySerialNumberString();
             util::decode_utf16(ptr as *const _)
         }
     }
 
     pub fn device_slot(&self, id: impl TryInto<DeviceId>) -> Result<Option<DeviceSlot>, DeviceSlotError> {
         if let Ok(id) = id.try_into() {
-            if self.num_slots()? >= id.into() {
-                return Ok(Some(DeviceSlot::new(id, self.lock.clone())));
+            if self.num_slots()? > id.to_index() {
+                return Ok(Some(DeviceSlot::new(id, self.lock.clone(), self.standard_range)));
             }
         }
 
@@ -54,14 +124,38 @@ impl Interface {
     }
 
     pub fn device_slots(&self) -> Result<impl Iterator<Item = DeviceSlot> + DoubleEndedIterator, DeviceSlotsError> {
-        let lock = self.lock.clone();
+        let (lock, standard_range) = (self.lock.clone(), self.standard_range);
         Ok((0..self.num_slots()?)
-            .map(move |id| DeviceSlot::new(DeviceId::from_index(id).unwrap(), lock.clone())))
+            .map(move |id| DeviceSlot::new(DeviceId::from_index(id).unwrap(), lock.clone(), standard_range)))
+    }
+
+    /// Subscribes to the force-feedback packets sent to vJoy devices. See `FfbPackets` for details
+    /// on the threading model.
+    pub fn ffb_packets(&self) -> FfbPackets {
+        FfbPackets::new(self.lock.clone())
+    }
+
+    /// Finds the first present device which satisfies the given requirements, without acquiring it.
+    pub fn find_device(&self, req: &Requirements) -> Result<Option<DeviceSlot>, DeviceSlotsError> {
+        Ok(self.present_devices()?
+            .find(|d| req.matches(d)))
+    }
+
+    /// Gets the first present device, without acquiring it. This is suitable for read-only
+    /// inspection of a device (e.g. its status or capabilities).
+    pub fn first_present_device(&self) -> Result<Option<DeviceSlot>, DeviceSlotsError> {
+        Ok(self.present_devices()?.next())
+    }
+
+    /// Gets the device slots whose devices are free to be acquired.
+    pub fn free_slots(&self) -> Result<impl DoubleEndedIterator<Item = DeviceSlot>, DeviceSlotsError> {
+        Ok(self.device_slots()?
+            .filter(|d| d.status().is_acquirable()))
     }
 
     pub fn num_devices(&self) -> Result<usize, NumDevicesError> {
         let mut num = 0;
-        let success = unsafe { vjoy_sys::GetNumberExistingVJD(&mut num) } != 0;
+        let success = unsafe { ffi::GetNumberExistingVJD(&mut num) } != 0;
 
 		success.then_some(num)
 			.ok_or(NumDevicesError::Failed)
@@ -76,29 +170,214 @@ impl Interface {
                 .map(|n| n as usize)
     }
 
+    /// The slot count is fixed while vJoy is loaded, so it is only queried once per `Interface`.
     #[cfg(not(feature = "const-slots"))]
     pub fn num_slots(&self) -> Result<usize, NumSlotsError> {
+        if let Some(&num) = self.num_slots.get() {
+            return Ok(num);
+        }
+
         let mut num = 0;
-        let success = unsafe { vjoy_sys::GetvJoyMaxDevices(&mut num) } != 0;
+        let success = unsafe { ffi::GetvJoyMaxDevices(&mut num) } != 0;
 
 		success.then_some(num)
             .ok_or(NumSlotsError::Failed)
 			.and_then(|n| u8::try_from(n)
                 .map_err(|_| NumSlotsError::Invalid)
-                .map(|n| n as usize))
+                .map(|n| *self.num_slots.get_or_init(|| n as usize)))
+    }
+
+    pub fn present_devices(&self) -> Result<impl DoubleEndedIterator<Item = DeviceSlot>, DeviceSlotsError> {
+        Ok(self.device_slots()?
+            .filter(|d| d.is_available()))
+    }
+
+    /// Relinquishes a device by its ID, even if no `OwnedDeviceSlot` for it exists (e.g. because one
+    /// was leaked).
+    ///
+    /// vJoy only allows a process to relinquish devices that it acquired, so this fails with
+    /// `RelinquishError::OwnedElsewhere` if the device is still busy afterwards. If an
+    /// `OwnedDeviceSlot` for the device does exist, it will no longer be able to update the device,
+    /// and dropping it will not relinquish the device again.
+    pub fn relinquish(&self, id: impl TryInto<DeviceId>) -> Result<(), RelinquishError> {
+        let slot = self.device_slot(id)?
+            .ok_or(RelinquishError::NoSuchSlot)?;
+
+        device::force_relinquish(slot.id());
+
+        match slot.status() {
+            Status::Busy => Err(RelinquishError::OwnedElsewhere),
+            _ => Ok(()),
+        }
+    }
+
+    /// Relinquishes every device that is acquired by this process, as with `relinquish`. Devices
+    /// acquired by other processes cannot be relinquished, so this fails with
+    /// `RelinquishError::OwnedElsewhere` if any device is still busy afterwards (after relinquishing
+    /// all of the others).
+    pub fn relinquish_all(&self) -> Result<(), RelinquishError> {
+        for slot in self.device_slots()?.filter(|d| d.status() == Status::Acquired) {
+            device::force_relinquish(slot.id());
+        }
+
+        if self.device_slots()?.any(|d| d.status() == Status::Busy) {
+            return Err(RelinquishError::OwnedElsewhere);
+        }
+
+        Ok(())
+    }
+
+    /// Resets every vJoy device in a single call, returning all of their inputs to their defaults.
+    ///
+    /// This affects all devices, including those acquired by other processes, so it should be used
+    /// with care. The buffered states of any `OwnedDeviceSlot`s are not updated, and will be sent
+    /// again by their next `apply`. vJoy does not report whether this succeeded.
+    pub fn reset_all(&self) {
+        unsafe { ffi::ResetAll(); }
+    }
+
+    pub fn scan(&self) -> ScanReport {
+        let versions = self.versions();
+
+        let devices = self.present_devices()
+            .map(|slots| slots
+                .map(|d| DeviceReport {
+                    id: d.id(),
+                    status: d.status(),
+                    capabilities: d.capabilities().ok(),
+                })
+                .collect())
+            .unwrap_or_default();
+
+        ScanReport {
+            sdk_version: versions.sdk_version(),
+            interface_version: versions.interface_version().ok(),
+            driver_version: versions.driver_version().ok(),
+
+            manufacturer: self.device_manufacturer().ok(),
+            product: self.device_product().ok(),
+            serial: self.device_serial().ok(),
+
+            num_devices: self.num_devices().ok(),
+            num_slots: self.num_slots().ok(),
+            devices,
+        }
+    }
+
+    /// Counts all device slots, broken down by status, in a single pass. Unlike `device_counts`,
+    /// this includes slots whose devices are missing.
+    pub fn status_summary(&self) -> Result<StatusSummary, DeviceSlotsError> {
+        Ok(StatusSummary::count(self.device_slots()?))
     }
 
     pub fn versions(&self) -> Versions {
         let (mut interface_version, mut driver_version) = (0u16, 0u16);
-        unsafe { vjoy_sys::DriverMatch(&mut interface_version, &mut driver_version); }
+        let matched = unsafe { ffi::DriverMatch(&mut interface_version, &mut driver_version) } != 0;
+
+        // DriverMatch's return value only reports whether the versions match, so a call that failed
+        // outright shows up as a mismatch with neither version filled in.
+        let missing = if !matched && interface_version == 0 && driver_version == 0 {
+            VersionError::CallFailed
+        } else {
+            VersionError::Failed
+        };
 
         Versions {
-            driver_version: Version::from_raw(driver_version).ok_or(VersionError::Failed),
-            interface_version: Version::from_raw(interface_version).ok_or(VersionError::Failed),
+            driver_version: Version::from_raw(driver_version).ok_or(missing),
+            interface_version: Version::from_raw(interface_version).ok_or(missing),
+            matched,
+        }
+    }
+}
+
+/// Configures options for an `Interface` before creating it.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct InterfaceBuilder {
+    standard_range: bool,
+}
+
+impl InterfaceBuilder {
+    pub fn new() -> InterfaceBuilder {
+        InterfaceBuilder::default()
+    }
+
+    /// If set, every axis of every device is assumed to use vJoy's default range of
+    /// `0..=VJOY_AXIS_MAX_VALUE`, and the driver is never queried for axis ranges. This is the
+    /// runtime equivalent of the `const-range` feature (which, if enabled, takes precedence).
+    pub fn assume_standard_range(mut self, assume: bool) -> InterfaceBuilder {
+        self.standard_range = assume;
+        self
+    }
+
+    pub fn build(self) -> Result<Interface, NewInterfaceError> {
+        Interface::with_options(self)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct DeviceCounts {
+    pub present: usize,
+    pub free: usize,
+    pub owned_by_us: usize,
+    pub busy: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct StatusSummary {
+    pub free: usize,
+    pub acquired: usize,
+    pub busy: usize,
+    pub missing: usize,
+    pub unknown: usize,
+}
+
+impl StatusSummary {
+    // Counts the statuses of the given slots, querying each slot's status once.
+    fn count(slots: impl Iterator<Item = DeviceSlot>) -> StatusSummary {
+        let mut summary = StatusSummary::default();
+
+        for slot in slots {
+            match slot.status() {
+                Status::Free => summary.free += 1,
+                Status::Acquired => summary.acquired += 1,
+                Status::Busy => summary.busy += 1,
+                Status::Missing => summary.missing += 1,
+                Status::Unknown => summary.unknown += 1,
+            }
         }
+
+        summary
+    }
+
+    fn total(&self) -> usize {
+        self.free + self.acquired + self.busy + self.missing + self.unknown
     }
 }
 
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
+pub enum AcquireAnyError {
+    #[error(transparent)]
+    Acquire(#[from] AcquireError),
+
+    #[error(transparent)]
+    DeviceSlots(#[from] DeviceSlotsError),
+
+    #[error("No vJoy device is free to be acquired.")]
+    NoneFree,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
+pub enum AcquireMatchingError {
+    #[error(transparent)]
+    Acquire(#[from] AcquireError),
+
+    #[error(transparent)]
+    DeviceSlots(#[from] DeviceSlotsError),
+
+    #[error("No free vJoy device satisfies the requirements.")]
+    NoMatch,
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
 pub enum DeviceSlotError {
     #[error(transparent)]
@@ -121,6 +400,9 @@ pub enum NewInterfaceError {
 
     #[error("No vJoy driver is available.")]
     NotAvailable,
+
+    #[error("An instance of the vJoy interface already exists on another thread ({owner:?}). vJoy may only be accessed from one thread at a time.")]
+    WrongThread { owner: LockOwner },
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
@@ -140,3 +422,128 @@ pub enum NumSlotsError {
     #[error("The vJoy Interface library returned an invalid number of device slots.")]
     Invalid,
 }
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
+pub enum RelinquishError {
+    #[error(transparent)]
+    DeviceSlot(#[from] DeviceSlotError),
+
+    #[error(transparent)]
+    DeviceSlots(#[from] DeviceSlotsError),
+
+    #[error("The device ID does not refer to a valid device slot.")]
+    NoSuchSlot,
+
+    #[error("The vJoy device is acquired by another process.")]
+    OwnedElsewhere,
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "mock")]
+    use super::*;
+    #[cfg(feature = "mock")]
+    use crate::Axis;
+    #[cfg(feature = "mock")]
+    use crate::mock::{TestInterface, test_device};
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn acquire_matching_without_match() {
+        let vjoy = TestInterface::new([test_device(), test_device()]);
+
+        let requirements = [
+            Requirements::new().buttons(17),
+            Requirements::new().axis(Axis::Slider),
+            Requirements::new().axes([Axis::X, Axis::Y]).cont_povs(2).disc_povs(1),
+        ];
+
+        for req in &requirements {
+            assert_eq!(vjoy.acquire_matching(req).unwrap_err(), AcquireMatchingError::NoMatch, "{:?}", req);
+        }
+
+        assert!(vjoy.present_devices().unwrap().all(|d| d.status() == Status::Free));
+        assert!(vjoy.acquire_matching(&Requirements::new().buttons(16)).is_ok());
+    }
+
+    #[cfg(all(feature = "mock", not(feature = "const-slots")))]
+    #[test]
+    fn slot_count_is_cached_per_interface() {
+        let _serial = crate::lock::serialize_test();
+        crate::mock::reset();
+
+        let vjoy = Interface::new().unwrap();
+        assert_eq!(vjoy.num_slots(), Ok(vjoy_sys::VJOY_MAX_N_DEVICES as usize));
+
+        crate::mock::set_num_slots(4);
+        assert_eq!(vjoy.num_slots(), Ok(vjoy_sys::VJOY_MAX_N_DEVICES as usize));
+        drop(vjoy);
+
+        let vjoy = Interface::new().unwrap();
+        assert_eq!(vjoy.num_slots(), Ok(4));
+        assert_eq!(vjoy.device_slots().unwrap().count(), 4);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn acquire_matching_reports_why_it_failed() {
+        let vjoy = TestInterface::new([test_device()]);
+        let req = Requirements::new().buttons(16);
+
+        crate::mock::set_enabled(false);
+        assert!(matches!(vjoy.acquire_matching(&req).unwrap_err(), AcquireMatchingError::Acquire(_)));
+        assert_eq!(vjoy.acquire_matching(&Requirements::new().buttons(17)).unwrap_err(), AcquireMatchingError::NoMatch);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn acquire_any_reports_why_it_failed() {
+        let vjoy = TestInterface::new([test_device(), test_device()]);
+        assert_eq!(vjoy.acquire_any().unwrap().id().to_raw(), 1);
+        assert_eq!(vjoy.acquire_any().unwrap().id().to_raw(), 1);
+
+        let held = vjoy.acquire(1);
+        crate::mock::set_busy(DeviceId::from_raw(2).unwrap(), true);
+        assert_eq!(vjoy.acquire_any().unwrap_err(), AcquireAnyError::NoneFree);
+
+        drop(held);
+        crate::mock::set_enabled(false);
+        assert!(matches!(vjoy.acquire_any().unwrap_err(), AcquireAnyError::Acquire(_)));
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn stale_slot_does_not_relinquish_new_owner() {
+        let vjoy = TestInterface::new([test_device()]);
+        let stale = vjoy.acquire(1);
+        let id = stale.id();
+
+        vjoy.relinquish(id).unwrap();
+        let current = vjoy.acquire(1);
+
+        drop(stale);
+        assert_eq!(current.status(), Status::Acquired);
+        assert!(current.apply().is_ok());
+
+        drop(current);
+        assert_eq!(vjoy.device_slot(id).unwrap().unwrap().status(), Status::Free);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn relinquish_all_releases_acquired_devices() {
+        let vjoy = TestInterface::new([test_device(), test_device(), test_device()]);
+        let first = vjoy.acquire(1);
+        let second = vjoy.acquire(2);
+        crate::mock::set_busy(DeviceId::from_raw(3).unwrap(), true);
+
+        assert_eq!(vjoy.relinquish_all(), Err(RelinquishError::OwnedElsewhere));
+        assert_eq!(first.status(), Status::Free);
+        assert_eq!(second.status(), Status::Free);
+        assert_eq!(vjoy.device_slot(3u8).unwrap().unwrap().status(), Status::Busy);
+
+        crate::mock::set_busy(DeviceId::from_raw(3).unwrap(), false);
+        let _first = vjoy.acquire(1);
+        assert_eq!(vjoy.relinquish_all(), Ok(()));
+    }
+}
@@ -1,12 +1,41 @@
+#[cfg(feature = "actor")]
+mod actor;
+mod capabilities;
 mod device;
+mod events;
+mod ffb;
+#[cfg(feature = "tokio")]
+mod feed;
+mod ffi;
 mod interface;
 mod lock;
+#[cfg(feature = "mock")]
+pub mod mock;
+mod probe;
+mod rate;
+mod report;
+mod state;
+mod subscription;
+mod timeline;
 mod util;
 mod version;
 
 pub use vjoy_sys as sys;
+#[cfg(feature = "actor")]
+pub use crate::actor::*;
+pub use crate::capabilities::*;
 pub use crate::device::*;
+pub use crate::events::*;
+pub use crate::ffb::*;
+#[cfg(feature = "tokio")]
+pub use crate::feed::*;
 pub use crate::interface::*;
+pub use crate::lock::{LockOwner};
+pub use crate::probe::*;
+pub use crate::rate::*;
+pub use crate::report::*;
+pub use crate::state::*;
+pub use crate::timeline::*;
 pub use crate::version::*;
 
 use crate::lock::{VJoyLock};
@@ -14,24 +43,41 @@ use crate::lock::{VJoyLock};
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
 #[error(transparent)]
 pub enum Error {
+	#[cfg(feature = "actor")]
+	Actor(#[from] ActorError),
+	AcquireAny(#[from] AcquireAnyError),
+	AcquireMatching(#[from] AcquireMatchingError),
 	DeviceSlot(#[from] DeviceSlotError),
 	DeviceSlots(#[from] DeviceSlotsError),
 	NewInterface(#[from] NewInterfaceError),
 	NumDevices(#[from] NumDevicesError),
 	NumSlots(#[from] NumSlotsError),
+	Relinquish(#[from] RelinquishError),
 
+	Acquire(#[from] AcquireError),
 	Apply(#[from] ApplyError),
 	AxisRange(#[from] AxisRangeError),
+	Capabilities(#[from] CapabilitiesError),
 	DeviceIdFromIndex(#[from] DeviceIdFromIndexError),
 	DeviceIdFromRaw(#[from] DeviceIdFromRawError),
 	GetAxis(#[from] GetAxisError),
 	NumButtons(#[from] NumButtonsError),
 	NumContPov(#[from] NumContPovError),
 	NumDiscPov(#[from] NumDiscPovError),
+	Owner(#[from] OwnerError),
+	ParseAxis(#[from] ParseAxisError),
+	ParseDeviceId(#[from] ParseDeviceIdError),
+	PressAndRelease(#[from] PressAndReleaseError),
+	Reset(#[from] ResetError),
 	SetAxis(#[from] SetAxisError),
 	SetButton(#[from] SetButtonError),
+	SetButtons(#[from] SetButtonsError),
+	SetPov(#[from] SetPovError),
 	TryIntoDeviceId(#[from] TryIntoDeviceIdError),
+	Wait(#[from] WaitError),
 
 	DriverVersion(#[from] DriverVersionError),
 	InterfaceVersion(#[from] InterfaceVersionError),
+	ParseVersion(#[from] ParseVersionError),
+	VersionMismatch(#[from] VersionMismatchError),
 }
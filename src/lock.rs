@@ -1,5 +1,9 @@
+use std::cell::{Cell};
+use std::fmt::{self, Debug, Formatter};
 use std::marker::{PhantomData};
+use std::sync::{Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, ThreadId};
 
 /// `VJoyLock` is used to ensure that all vJoy access is contained to a single thread at any given
 /// point in time, while introducing minimal overhead.
@@ -10,16 +14,16 @@ use std::sync::atomic::{AtomicBool, Ordering};
 ///
 /// Access to a `VJoyLock` ensures that all vJoy access occurs on the same thread. This occurs by
 /// ensuring that a `VJoyLock` can only be created if none others exist (tracked via the AtomicBool
-/// `LOCKED`) or is cloned from an existing `VJoyLock`. Since `VJoyLock` is neither Send nor Sync,
-/// this means that all `VJoyLock` objects that exist at any given time must exist on the same
-/// thread. Thus, by ensuring that all vJoy access occurs in the presence of a `VJoyLock`, all vJoy
-/// access is limited to a single thread.
+/// `LOCKED`, which is only changed while the `OWNER` mutex is held, so that `OWNER` always names the
+/// thread holding the lock) or is cloned from an existing `VJoyLock`. Since `VJoyLock` is neither
+/// Send nor Sync, this means that all `VJoyLock` objects that exist at any given time must exist on
+/// the same thread. Thus, by ensuring that all vJoy access occurs in the presence of a `VJoyLock`,
+/// all vJoy access is limited to a single thread.
 ///
 /// A reference count is maintained to keep track of the number of existing `VJoyLock` objects.
-/// Since all `VJoyLock` objects exist on the same thread, this can safely be stored in a `static
-/// mut`, allowing `VJoyLock` to be a zero-sized struct, while also preventing the need for the
-/// overhead of atomic reference counting. This should be sound as long as that reference count is
-/// only ever accessed in the presence of a `VJoyLock` object.
+/// Since all `VJoyLock` objects exist on the same thread, this can be stored in a thread-local
+/// `Cell`, allowing `VJoyLock` to be a zero-sized struct, while also preventing the need for the
+/// overhead of atomic reference counting.
 #[derive(Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct VJoyLock(PhantomData<*const ()>);
 
@@ -27,29 +31,33 @@ pub struct VJoyLock(PhantomData<*const ()>);
 static_assertions::assert_not_impl_any!(VJoyLock: Send, Sync);
 
 impl VJoyLock {
-    pub fn new() -> Option<VJoyLock> {
+    /// Creates a `VJoyLock` if none exist, or returns the thread which holds the lock.
+    ///
+    /// The owner is read under the same mutex that guards taking and releasing the lock, so it is
+    /// always the thread that held the lock at the moment this failed.
+    pub fn new() -> Result<VJoyLock, Option<LockOwner>> {
+        let mut owner = OWNER.lock().unwrap();
+
         if LOCKED.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok() {
-            // UNSAFE: Because LOCKED was just changed from false to true, there must not be any
-            // existing VJoyLock objects; since REFS is only accessed by VJoyLock objects, this
-            // means that accessing REFS is safe.
-            unsafe {
-                debug_assert_eq!(REFS, 0);
-                REFS = 1;
-            }
-
-            Some(VJoyLock(PhantomData))
+            *owner = Some(LockOwner::current());
+
+            // Because LOCKED was just changed from false to true, there must not be any existing
+            // VJoyLock objects, on this thread or any other.
+            REFS.with(|refs| {
+                debug_assert_eq!(refs.get(), 0);
+                refs.set(1);
+            });
+
+            Ok(VJoyLock(PhantomData))
         } else {
-            None
+            Err(*owner)
         }
     }
 }
 
 impl Clone for VJoyLock {
     fn clone(&self) -> Self {
-        // UNSAFE: This is in the presence of a VJoyLock (self), so accessing REFS is safe.
-        unsafe {
-            REFS += 1;
-        }
+        REFS.with(|refs| refs.set(refs.get() + 1));
 
         VJoyLock(PhantomData)
     }
@@ -57,21 +65,153 @@ impl Clone for VJoyLock {
 
 impl Drop for VJoyLock {
     fn drop(&mut self) {
-        // UNSAFE: This is in the presence of a VJoyLock (self), so accessing REFS is safe.
-        let unlock = unsafe {
-            debug_assert!(REFS > 0);
-            REFS -= 1;
+        let unlock = REFS.with(|refs| {
+            debug_assert!(refs.get() > 0);
+            refs.set(refs.get() - 1);
 
             // If REFS was set to 0, then self is the last existing VJoyLock object, and LOCKED may
             // now be set back to false so that new VJoyLocks may be created.
-            REFS == 0
-        };
+            refs.get() == 0
+        });
 
         if unlock {
+            let mut owner = OWNER.lock().unwrap();
+            *owner = None;
             LOCKED.store(false, Ordering::Release);
         }
     }
 }
 
+/// `LockOwner` is the thread which holds the vJoy lock, as reported when another thread fails to
+/// take it. Its `Debug` representation is that of its `ThreadId`.
+///
+/// `ThreadId` does not implement `Ord`, but each thread is given a unique number when it is
+/// created, which its `Debug` representation shows (e.g. `ThreadId(12)`). Owners are ordered by
+/// that number.
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct LockOwner(ThreadId);
+
+impl LockOwner {
+    fn current() -> LockOwner {
+        LockOwner(thread::current().id())
+    }
+
+    /// Gets the ID of the thread which holds the lock.
+    pub fn thread_id(&self) -> ThreadId {
+        self.0
+    }
+
+    // The number shown by the ID's `Debug` representation, along with the representation itself,
+    // which breaks ties (keeping the ordering consistent with `Eq`) should the number be missing.
+    fn sort_key(&self) -> (Option<u64>, String) {
+        let debug = format!("{:?}", self.0);
+        let number = debug.chars()
+            .filter(char::is_ascii_digit)
+            .collect::<String>()
+            .parse()
+            .ok();
+
+        (number, debug)
+    }
+}
+
+impl Debug for LockOwner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl Ord for LockOwner {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        if self == other {
+            return std::cmp::Ordering::Equal;
+        }
+
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl PartialOrd for LockOwner {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Serializes tests which take a `VJoyLock`, since only one thread may hold one at a time.
+#[cfg(test)]
+pub(crate) fn serialize_test() -> std::sync::MutexGuard<'static, ()> {
+    static TESTS: Mutex<()> = Mutex::new(());
+    TESTS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
 static LOCKED: AtomicBool = AtomicBool::new(false);
-static mut REFS: usize = 0;
+static OWNER: Mutex<Option<LockOwner>> = Mutex::new(None);
+
+thread_local! {
+    static REFS: Cell<usize> = const { Cell::new(0) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owner() -> Option<ThreadId> {
+        OWNER.lock().unwrap().map(|owner| owner.thread_id())
+    }
+
+    fn refs() -> usize {
+        REFS.with(Cell::get)
+    }
+
+    #[test]
+    fn clone_and_drop_balance() {
+        let _serial = serialize_test();
+
+        let lock = VJoyLock::new().unwrap();
+        assert_eq!(refs(), 1);
+        assert_eq!(owner(), Some(thread::current().id()));
+
+        let clones = vec![lock.clone(), lock.clone()];
+        assert_eq!(refs(), 3);
+        assert_eq!(VJoyLock::new().err(), Some(Some(LockOwner::current())));
+
+        drop(clones);
+        assert_eq!(refs(), 1);
+        assert!(LOCKED.load(Ordering::Relaxed));
+
+        drop(lock);
+        assert_eq!(refs(), 0);
+        assert!(!LOCKED.load(Ordering::Relaxed));
+        assert_eq!(owner(), None);
+
+        let relocked = VJoyLock::new();
+        assert!(relocked.is_ok());
+        assert_eq!(refs(), 1);
+    }
+
+    #[test]
+    fn other_threads_cannot_lock() {
+        let _serial = serialize_test();
+
+        let lock = VJoyLock::new().unwrap();
+        let this = LockOwner::current();
+        let other = thread::spawn(|| VJoyLock::new().err().flatten()).join().unwrap();
+        assert_eq!(other.map(|owner| owner.thread_id()), Some(this.thread_id()));
+        assert_eq!(other, Some(this));
+
+        drop(lock);
+        assert!(thread::spawn(|| VJoyLock::new().is_ok()).join().unwrap());
+    }
+
+    #[test]
+    fn owners_are_ordered_by_thread_id() {
+        let this = LockOwner::current();
+        let other = thread::spawn(LockOwner::current).join().unwrap();
+
+        // A thread spawned later has a larger ID.
+        assert_ne!(this, other);
+        assert!(this < other);
+        assert_eq!(this.cmp(&LockOwner::current()), std::cmp::Ordering::Equal);
+        assert_eq!(format!("{:?}", this), format!("{:?}", this.thread_id()));
+    }
+}
@@ -0,0 +1,512 @@
+//! A simulated vJoy driver, enabled by the `mock` feature.
+//!
+//! With the `mock` feature enabled, all calls which would be made into the vJoy interface library are
+//! instead handled by an in-process simulation, allowing code which uses this crate to be tested
+//! without vJoy installed (including on platforms other than Windows). The simulated driver starts
+//! out enabled with no devices; use `add_device` to configure some.
+//!
+//! The simulated driver's state is thread-local. Since vJoy may only be accessed from a single
+//! thread at a time, it should be configured from the same thread that creates the `Interface`.
+//! In particular, a `VJoyActor` accesses vJoy from a thread of its own, so it never sees simulated
+//! devices configured on any other thread.
+
+use std::cell::{RefCell};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::{RangeInclusive};
+
+use crate::{Axis, DeviceId, DeviceState, FfbEffect, Status};
+#[cfg(test)]
+use crate::{Interface};
+
+/// The configuration of a simulated vJoy device.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct MockDevice {
+	pub num_buttons: usize,
+	pub num_cont_pov: usize,
+	pub num_disc_pov: usize,
+	pub axes: BTreeMap<Axis, RangeInclusive<i32>>,
+	pub ffb: bool,
+	/// The force-feedback effects the device supports. These are ignored unless `ffb` is set.
+	pub ffb_effects: BTreeSet<FfbEffect>,
+}
+
+/// Adds a simulated device in the given slot, replacing any device already there.
+pub fn add_device(id: DeviceId, device: MockDevice) {
+	with_driver(|d| d.devices.insert(id.to_raw(), Device {
+		config: device,
+		status: Status::Free,
+		applied: None,
+	}));
+}
+
+/// Returns the state most recently sent to a simulated device, or `None` if no state has been
+/// sent to it since it was added or reset.
+pub fn applied_state(id: DeviceId) -> Option<DeviceState> {
+	with_driver(|d| d.devices.get(&id.to_raw()).and_then(|dev| dev.applied))
+}
+
+/// Returns the number of times the simulated driver has been asked for the minimum of an axis
+/// since it was last reset. This crate uses that query both to retrieve axis ranges and to check
+/// whether an axis exists.
+pub fn axis_range_queries() -> usize {
+	with_driver(|d| d.axis_range_queries)
+}
+
+/// Removes the simulated device in the given slot, if any.
+pub fn remove_device(id: DeviceId) {
+	with_driver(|d| d.devices.remove(&id.to_raw()));
+}
+
+/// Removes all simulated devices and re-enables the simulated driver.
+pub fn reset() {
+	with_driver(|d| *d = Driver::new());
+}
+
+/// The process ID reported as the owner of simulated devices marked busy with `set_busy`.
+pub const OTHER_PROCESS_PID: u32 = 4242;
+
+/// Marks a simulated device as acquired (or no longer acquired) by another process.
+pub fn set_busy(id: DeviceId, busy: bool) {
+	with_driver(|d| if let Some(dev) = d.devices.get_mut(&id.to_raw()) {
+		dev.status = if busy { Status::Busy } else { Status::Free };
+	});
+}
+
+/// Sets the number of device slots that the simulated driver reports. This is vJoy's maximum until
+/// it is changed.
+pub fn set_num_slots(num: u8) {
+	with_driver(|d| d.num_slots = num);
+}
+
+/// Sets whether the simulated driver reports itself as enabled. While it is disabled, devices
+/// cannot be acquired.
+pub fn set_enabled(enabled: bool) {
+	with_driver(|d| d.enabled = enabled);
+}
+
+struct Driver {
+	enabled: bool,
+	devices: BTreeMap<u8, Device>,
+	num_slots: u8,
+	axis_range_queries: usize,
+}
+
+impl Driver {
+	fn new() -> Driver {
+		Driver {
+			enabled: true,
+			devices: BTreeMap::new(),
+			num_slots: vjoy_sys::VJOY_MAX_N_DEVICES as u8,
+			axis_range_queries: 0,
+		}
+	}
+}
+
+struct Device {
+	config: MockDevice,
+	status: Status,
+	applied: Option<DeviceState>,
+}
+
+impl Device {
+	fn axis_range(&self, usage: u32) -> Option<&RangeInclusive<i32>> {
+		self.config.axes.get(&Axis::from_usage(usage)?)
+	}
+
+	fn reset(&mut self, id: u8) {
+		let mut state = DeviceState::new(DeviceId::from_raw(id).unwrap());
+		for (axis, range) in &self.config.axes {
+			let (lo, hi) = (*range.start() as i64, *range.end() as i64);
+			state.set_axis(*axis, (lo + (hi - lo) / 2) as i32);
+		}
+
+		state.center_povs();
+		self.applied = Some(state);
+	}
+}
+
+thread_local! {
+	static DRIVER: RefCell<Driver> = RefCell::new(Driver::new());
+}
+
+fn with_driver<R>(f: impl FnOnce(&mut Driver) -> R) -> R {
+	DRIVER.with(|d| f(&mut d.borrow_mut()))
+}
+
+fn with_device<R>(id: vjoy_sys::UINT, f: impl FnOnce(&mut Device) -> R) -> Option<R> {
+	let id = u8::try_from(id).ok()?;
+	with_driver(|d| d.devices.get_mut(&id).map(f))
+}
+
+// Stand-ins for the functions of the vJoy interface library, with the same signatures. As in `ffi`,
+// some go unused depending on which features are enabled (e.g. `const-range`).
+#[allow(dead_code, non_snake_case)]
+pub(crate) mod ffi {
+	use std::os::raw::{c_int};
+
+	use vjoy_sys::{
+		BOOL, BYTE, DWORD, FFB_DATA, FFB_EFF_COND, FFB_EFF_CONSTANT, FFB_EFF_ENVLP, FFB_EFF_OP, FFB_EFF_PERIOD,
+		FFB_EFF_RAMP, FFB_EFF_REPORT, FFBPType, FfbGenCB, LONG, PVOID, RemovalCB, UINT, VjdStat, WORD,
+	};
+
+	use crate::{DeviceId, DeviceState, Status};
+	use super::{with_device, with_driver};
+
+	static MANUFACTURER: [u16; 5] = utf16("Mock");
+	static PRODUCT: [u16; 12] = utf16("vJoy (mock)");
+	static SERIAL: [u16; 2] = utf16("0");
+
+	// Any nonzero value indicates failure for the packet parsing functions.
+	const PARSE_FAILED: DWORD = 1;
+
+	const fn utf16<const N: usize>(s: &str) -> [u16; N] {
+		let bytes = s.as_bytes();
+		let mut out = [0; N];
+
+		let mut i = 0;
+		while i < bytes.len() {
+			out[i] = bytes[i] as u16;
+			i += 1;
+		}
+
+		out
+	}
+
+	// Like vJoy's parsers, this reads the packet's data without checking its size: the output is
+	// simply copied from the bytes following the report ID.
+	unsafe fn read_report<T>(Packet: *const FFB_DATA, out: *mut T) -> DWORD {
+		match Packet.as_ref() {
+			Some(packet) if !packet.data.is_null() => {
+				std::ptr::copy_nonoverlapping(packet.data.add(1), out as *mut u8, size_of::<T>());
+				0
+			},
+			_ => PARSE_FAILED,
+		}
+	}
+
+	pub unsafe fn AcquireVJD(rID: UINT) -> BOOL {
+		if !with_driver(|d| d.enabled) {
+			return 0;
+		}
+
+		with_device(rID, |dev| match dev.status {
+			Status::Free | Status::Acquired => {
+				dev.status = Status::Acquired;
+				1
+			},
+			_ => 0,
+		}).unwrap_or(0)
+	}
+
+	pub unsafe fn DriverMatch(DllVer: *mut WORD, DrvVer: *mut WORD) -> BOOL {
+		*DllVer = vjoy_sys::VERSION_N as WORD;
+		*DrvVer = vjoy_sys::VERSION_N as WORD;
+		1
+	}
+
+	pub unsafe fn FfbRegisterGenCB(_cb: FfbGenCB, _data: PVOID) {}
+
+	pub unsafe extern "C" fn Ffb_h_DevGain(Packet: *const FFB_DATA, Gain: *mut BYTE) -> DWORD {
+		read_report(Packet, Gain)
+	}
+
+	// Like vJoy's, this takes the device ID from the high nibble of the packet's report ID.
+	pub unsafe extern "C" fn Ffb_h_DeviceID(Packet: *const FFB_DATA, DeviceID: *mut c_int) -> DWORD {
+		match Packet.as_ref() {
+			Some(packet) if !packet.data.is_null() && packet.size > 8 => {
+				*DeviceID = (*packet.data >> 4) as c_int;
+				0
+			},
+			_ => PARSE_FAILED,
+		}
+	}
+
+	pub unsafe extern "C" fn Ffb_h_EffOp(Packet: *const FFB_DATA, Operation: *mut FFB_EFF_OP) -> DWORD {
+		read_report(Packet, Operation)
+	}
+
+	pub unsafe extern "C" fn Ffb_h_Eff_Cond(Packet: *const FFB_DATA, Condition: *mut FFB_EFF_COND) -> DWORD {
+		read_report(Packet, Condition)
+	}
+
+	pub unsafe extern "C" fn Ffb_h_Eff_Constant(Packet: *const FFB_DATA, ConstantEffect: *mut FFB_EFF_CONSTANT) -> DWORD {
+		read_report(Packet, ConstantEffect)
+	}
+
+	pub unsafe extern "C" fn Ffb_h_Eff_Envlp(Packet: *const FFB_DATA, Envelope: *mut FFB_EFF_ENVLP) -> DWORD {
+		read_report(Packet, Envelope)
+	}
+
+	pub unsafe extern "C" fn Ffb_h_Eff_Period(Packet: *const FFB_DATA, Effect: *mut FFB_EFF_PERIOD) -> DWORD {
+		read_report(Packet, Effect)
+	}
+
+	pub unsafe extern "C" fn Ffb_h_Eff_Ramp(Packet: *const FFB_DATA, RampEffect: *mut FFB_EFF_RAMP) -> DWORD {
+		read_report(Packet, RampEffect)
+	}
+
+	pub unsafe extern "C" fn Ffb_h_Eff_Report(Packet: *const FFB_DATA, Effect: *mut FFB_EFF_REPORT) -> DWORD {
+		read_report(Packet, Effect)
+	}
+
+	pub unsafe extern "C" fn Ffb_h_Type(_Packet: *const FFB_DATA, _Type: *mut FFBPType) -> DWORD {
+		PARSE_FAILED
+	}
+
+	pub unsafe fn GetNumberExistingVJD(n: *mut c_int) -> BOOL {
+		*n = with_driver(|d| d.devices.len()) as c_int;
+		1
+	}
+
+	pub unsafe fn GetOwnerPid(rID: UINT) -> c_int {
+		with_device(rID, |dev| match dev.status {
+			Status::Acquired => std::process::id() as c_int,
+			Status::Busy => super::OTHER_PROCESS_PID as c_int,
+			_ => vjoy_sys::BAD_DEV_STAT,
+		}).unwrap_or(vjoy_sys::NO_DEV_EXIST)
+	}
+
+	pub unsafe fn GetVJDAxisMax(rID: UINT, Axis: UINT, Max: *mut LONG) -> BOOL {
+		with_device(rID, |dev| dev.axis_range(Axis).map(|r| *Max = *r.end()))
+			.flatten()
+			.is_some() as BOOL
+	}
+
+	pub unsafe fn GetVJDAxisMin(rID: UINT, Axis: UINT, Min: *mut LONG) -> BOOL {
+		with_driver(|d| d.axis_range_queries += 1);
+		with_device(rID, |dev| dev.axis_range(Axis).map(|r| *Min = *r.start()))
+			.flatten()
+			.is_some() as BOOL
+	}
+
+	pub unsafe fn GetVJDButtonNumber(rID: UINT) -> c_int {
+		with_device(rID, |dev| dev.config.num_buttons as c_int).unwrap_or(-1)
+	}
+
+	pub unsafe fn GetVJDContPovNumber(rID: UINT) -> c_int {
+		with_device(rID, |dev| dev.config.num_cont_pov as c_int).unwrap_or(-1)
+	}
+
+	pub unsafe fn GetVJDDiscPovNumber(rID: UINT) -> c_int {
+		with_device(rID, |dev| dev.config.num_disc_pov as c_int).unwrap_or(-1)
+	}
+
+	pub unsafe fn GetVJDStatus(rID: UINT) -> VjdStat {
+		with_device(rID, |dev| dev.status)
+			.unwrap_or(Status::Missing)
+			.into()
+	}
+
+	pub unsafe fn GetvJoyManufacturerString() -> PVOID {
+		MANUFACTURER.as_ptr() as PVOID
+	}
+
+	pub unsafe fn GetvJoyMaxDevices(n: *mut c_int) -> BOOL {
+		*n = with_driver(|d| d.num_slots) as c_int;
+		1
+	}
+
+	pub unsafe fn GetvJoyProductString() -> PVOID {
+		PRODUCT.as_ptr() as PVOID
+	}
+
+	pub unsafe fn GetvJoySerialNumberString() -> PVOID {
+		SERIAL.as_ptr() as PVOID
+	}
+
+	pub unsafe fn IsDeviceFfb(rID: UINT) -> BOOL {
+		with_device(rID, |dev| dev.config.ffb as BOOL).unwrap_or(0)
+	}
+
+	pub unsafe fn IsDeviceFfbEffect(rID: UINT, Effect: UINT) -> BOOL {
+		with_device(rID, |dev| {
+			let supported = dev.config.ffb_effects.iter().any(|e| e.usage() == Effect);
+			(dev.config.ffb && supported) as BOOL
+		}).unwrap_or(0)
+	}
+
+	pub unsafe fn RegisterRemovalCB(_cb: RemovalCB, _data: PVOID) {}
+
+	pub unsafe fn RelinquishVJD(rID: UINT) {
+		with_device(rID, |dev| if dev.status == Status::Acquired {
+			dev.status = Status::Free;
+		});
+	}
+
+	pub unsafe fn ResetAll() {
+		with_driver(|d| for (id, dev) in &mut d.devices {
+			dev.reset(*id);
+		});
+	}
+
+	pub unsafe fn ResetButtons(rID: UINT) -> BOOL {
+		with_owned_state(rID, |s| s.clear_buttons())
+	}
+
+	pub unsafe fn ResetPovs(rID: UINT) -> BOOL {
+		with_owned_state(rID, |s| s.center_povs())
+	}
+
+	pub unsafe fn ResetVJD(rID: UINT) -> BOOL {
+		with_device(rID, |dev| if dev.status == Status::Acquired {
+			dev.reset(rID as u8);
+			1
+		} else {
+			0
+		}).unwrap_or(0)
+	}
+
+	pub unsafe fn UpdateVJD(rID: UINT, pData: PVOID) -> BOOL {
+		let state = *(pData as *const DeviceState);
+		with_device(rID, |dev| if dev.status == Status::Acquired {
+			dev.applied = Some(state);
+			1
+		} else {
+			0
+		}).unwrap_or(0)
+	}
+
+	pub unsafe fn isVJDExists(rID: UINT) -> BOOL {
+		with_device(rID, |_| 1).unwrap_or(0)
+	}
+
+	pub unsafe fn vJoyEnabled() -> BOOL {
+		with_driver(|d| d.enabled) as BOOL
+	}
+
+	fn with_owned_state(rID: UINT, f: impl FnOnce(&mut DeviceState)) -> BOOL {
+		with_device(rID, |dev| {
+			if dev.status != Status::Acquired {
+				return 0;
+			}
+
+			let mut state = dev.applied.unwrap_or_else(|| DeviceState::new(DeviceId::from_raw(rID as u8).unwrap()));
+			f(&mut state);
+			dev.applied = Some(state);
+			1
+		}).unwrap_or(0)
+	}
+}
+
+/// An `Interface` backed by a freshly reset simulated driver, for tests. The devices are added in
+/// slots starting from 1. Holding it keeps other tests from taking the `VJoyLock` at the same time.
+#[cfg(test)]
+pub(crate) struct TestInterface {
+	vjoy: Interface,
+	_serial: std::sync::MutexGuard<'static, ()>,
+}
+
+#[cfg(test)]
+impl TestInterface {
+	pub(crate) fn new(devices: impl IntoIterator<Item = MockDevice>) -> TestInterface {
+		let serial = crate::lock::serialize_test();
+
+		reset();
+		for (id, device) in (1..).zip(devices) {
+			add_device(DeviceId::from_raw(id).unwrap(), device);
+		}
+
+		TestInterface {
+			vjoy: Interface::new().unwrap(),
+			_serial: serial,
+		}
+	}
+
+	/// Acquires the device in the given slot, which must be free.
+	pub(crate) fn acquire(&self, id: u8) -> crate::OwnedDeviceSlot {
+		self.vjoy.device_slot(DeviceId::from_raw(id).unwrap()).unwrap().unwrap()
+			.acquire().unwrap()
+	}
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TestInterface {
+	type Target = Interface;
+
+	fn deref(&self) -> &Interface {
+		&self.vjoy
+	}
+}
+
+/// A `TestInterface` with a single `test_device`, already acquired. Bind both as
+/// `let (vjoy, device) = test_slot();` so that the device is dropped before the interface.
+#[cfg(test)]
+pub(crate) fn test_slot() -> (TestInterface, crate::OwnedDeviceSlot) {
+	let vjoy = TestInterface::new([test_device()]);
+	let device = vjoy.acquire(1);
+	(vjoy, device)
+}
+
+/// A device with the X, Y, and Z axes (using vJoy's default range), 16 buttons, and 2 continuous
+/// POVs, for tests.
+#[cfg(test)]
+pub(crate) fn test_device() -> MockDevice {
+	MockDevice {
+		num_buttons: 16,
+		num_cont_pov: 2,
+		axes: [Axis::X, Axis::Y, Axis::Z].into_iter()
+			.map(|a| (a, 0..=vjoy_sys::VJOY_AXIS_MAX_VALUE as i32))
+			.collect(),
+		..MockDevice::default()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn id(raw: u8) -> DeviceId {
+		DeviceId::from_raw(raw).unwrap()
+	}
+
+	#[test]
+	fn acquire_changes_status() {
+		let vjoy = TestInterface::new([test_device()]);
+		let slot = vjoy.device_slot(id(1)).unwrap().unwrap();
+		assert_eq!(slot.status(), Status::Free);
+
+		let device = slot.acquire().unwrap();
+		assert_eq!(device.status(), Status::Acquired);
+
+		drop(device);
+		assert_eq!(vjoy.device_slot(id(1)).unwrap().unwrap().status(), Status::Free);
+	}
+
+	#[test]
+	fn busy_and_missing_devices_cannot_be_acquired() {
+		let vjoy = TestInterface::new([test_device()]);
+		set_busy(id(1), true);
+
+		let busy = vjoy.device_slot(id(1)).unwrap().unwrap();
+		assert_eq!(busy.status(), Status::Busy);
+		assert!(busy.acquire().is_err());
+
+		let missing = vjoy.device_slot(id(2)).unwrap().unwrap();
+		assert_eq!(missing.status(), Status::Missing);
+		assert!(missing.acquire().is_err());
+	}
+
+	#[test]
+	fn apply_sends_buffered_state() {
+		let (_vjoy, device) = test_slot();
+
+		device.set_axis_raw(Axis::Y, 1234).unwrap();
+		device.set_button(3, true).unwrap();
+		assert!(applied_state(id(1)).is_none());
+
+		device.apply().unwrap();
+		let applied = applied_state(id(1)).unwrap();
+		assert_eq!(applied.axis(Axis::Y), 1234);
+		assert_eq!(applied.button(3), Some(true));
+		assert_eq!(applied.button(4), Some(false));
+	}
+
+	#[test]
+	fn disabled_driver_is_not_available() {
+		let _serial = crate::lock::serialize_test();
+		reset();
+		set_enabled(false);
+
+		assert!(Interface::new().is_err());
+	}
+}
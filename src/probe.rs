@@ -0,0 +1,44 @@
+use crate::ffi;
+
+/// The result of probing the vJoy driver with `probe`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct ProbeResult {
+	/// Whether a vJoy driver is installed and enabled.
+	pub enabled: bool,
+}
+
+/// Checks whether vJoy is installed and enabled, without creating an `Interface` or taking the vJoy
+/// lock, so that it never causes an `Interface` to fail to be created on another thread.
+///
+/// This calls `vJoyEnabled` directly. vJoy may only be accessed from one thread at a time, and this
+/// call is not exempt from that: it touches the vJoy interface library's state, so calling it from
+/// one thread while another thread is using vJoy (e.g. through an `Interface`) races with that
+/// thread. It is only guaranteed to be safe if no `Interface` exists, or if it is called from the
+/// thread which owns the `Interface`. A monitoring thread should only call it while it knows that
+/// vJoy is otherwise idle.
+pub fn probe() -> ProbeResult {
+	let enabled = unsafe { ffi::vJoyEnabled() } != 0;
+	ProbeResult { enabled }
+}
+
+#[cfg(test)]
+mod tests {
+	#[cfg(feature = "mock")]
+	use super::*;
+	#[cfg(feature = "mock")]
+	use crate::mock::{TestInterface};
+
+	#[cfg(feature = "mock")]
+	#[test]
+	fn probe_does_not_take_the_lock() {
+		// The lock is held by the `Interface`, which would make taking it again fail.
+		let _vjoy = TestInterface::new([]);
+
+		crate::mock::set_enabled(false);
+		assert_eq!(probe(), ProbeResult { enabled: false });
+
+		crate::mock::set_enabled(true);
+		assert_eq!(probe(), ProbeResult { enabled: true });
+	}
+}
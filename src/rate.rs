@@ -0,0 +1,88 @@
+use std::time::{Duration, Instant};
+
+/// A `RateLimiter` enforces a minimum interval between events, such as calls to
+/// `OwnedDeviceSlot::apply`.
+///
+/// Events are scheduled on a fixed grid of ticks, so small delays in one event do not push back the
+/// rest. If the limiter falls more than a full interval behind, the grid is restarted from the
+/// current time rather than allowing a burst of events to catch up.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RateLimiter {
+	interval: Duration,
+	next: Option<Instant>,
+}
+
+impl RateLimiter {
+	/// Creates a limiter which allows `rate_hz` events per second, or `None` if the rate is zero or
+	/// above 1 GHz (where the interval would round down to zero nanoseconds).
+	pub fn new(rate_hz: u32) -> Option<RateLimiter> {
+		if rate_hz == 0 {
+			return None;
+		}
+
+		RateLimiter::with_interval(Duration::from_secs(1) / rate_hz)
+	}
+
+	/// Creates a limiter which allows one event per `interval`, or `None` if the interval is zero.
+	pub fn with_interval(interval: Duration) -> Option<RateLimiter> {
+		(!interval.is_zero()).then_some(RateLimiter {
+			interval,
+			next: None,
+		})
+	}
+
+	pub fn interval(&self) -> Duration {
+		self.interval
+	}
+
+	/// The number of events allowed per second.
+	pub fn rate_hz(&self) -> f64 {
+		1.0 / self.interval.as_secs_f64()
+	}
+
+	/// Returns how long until the next event is allowed, or zero if it is allowed now.
+	pub fn remaining(&self) -> Duration {
+		self.next.map_or(Duration::ZERO, |n| n.saturating_duration_since(Instant::now()))
+	}
+
+	/// Records an event if one is allowed now, returning whether it was. If this returns `false`,
+	/// the event should be skipped.
+	pub fn try_tick(&mut self) -> bool {
+		let now = Instant::now();
+		match self.next {
+			Some(next) if now < next => false,
+			_ => {
+				self.advance(now);
+				true
+			},
+		}
+	}
+
+	/// Sleeps until the next event is allowed, then records it.
+	pub fn wait(&mut self) {
+		std::thread::sleep(self.remaining());
+		self.advance(Instant::now());
+	}
+
+	fn advance(&mut self, now: Instant) {
+		let next = self.next.unwrap_or(now) + self.interval;
+		self.next = Some(if next < now { now + self.interval } else { next });
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn zero_interval_is_rejected() {
+		assert_eq!(RateLimiter::new(0), None);
+		assert_eq!(RateLimiter::with_interval(Duration::ZERO), None);
+		assert_eq!(RateLimiter::new(1_000_000_001), None);
+		assert!(RateLimiter::new(1_000_000_000).is_some());
+
+		let limiter = RateLimiter::with_interval(Duration::from_millis(8)).unwrap();
+		assert_eq!(limiter.interval(), Duration::from_millis(8));
+		assert_eq!(limiter.rate_hz(), 125.0);
+	}
+}
@@ -0,0 +1,29 @@
+use crate::{Capabilities, DeviceId, Status, Version};
+
+/// A `ScanReport` is a snapshot of the state of the vJoy driver and all of its present devices, as
+/// produced by `Interface::scan`.
+///
+/// Any information which could not be retrieved is left as `None`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ScanReport {
+	pub sdk_version: Version,
+	pub interface_version: Option<Version>,
+	pub driver_version: Option<Version>,
+
+	pub manufacturer: Option<String>,
+	pub product: Option<String>,
+	pub serial: Option<String>,
+
+	pub num_devices: Option<usize>,
+	pub num_slots: Option<usize>,
+	pub devices: Vec<DeviceReport>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DeviceReport {
+	pub id: DeviceId,
+	pub status: Status,
+	pub capabilities: Option<Capabilities>,
+}
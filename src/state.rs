@@ -0,0 +1,412 @@
+use std::ops::{BitOr};
+
+use crate::{Axis, Capabilities, DeviceId, MAX_BUTTONS, MAX_POVS, MAX_POV_CENTIDEGREES, PovDirection, SetButtonError, SetPovError};
+use crate::device::{STANDARD_AXIS_RANGE, axis_center};
+
+/// A `DeviceState` is a copy of the full set of inputs sent to a vJoy device when it is updated.
+/// Values written through it are not validated against any device's capabilities.
+#[derive(Clone, Copy, Debug)]
+#[repr(transparent)]
+pub struct DeviceState(vjoy_sys::JOYSTICK_POSITION);
+
+impl DeviceState {
+	pub(crate) fn new(id: DeviceId) -> DeviceState {
+		DeviceState(vjoy_sys::JOYSTICK_POSITION {
+			bDevice: id.into(),
+			..unsafe { std::mem::zeroed() }
+		})
+	}
+
+	pub(crate) fn raw_mut(&mut self) -> &mut vjoy_sys::JOYSTICK_POSITION {
+		&mut self.0
+	}
+
+	pub fn axis(&self, axis: Axis) -> i32 {
+		let state = &self.0;
+		match axis {
+			Axis::X => state.wAxisX,
+			Axis::Y => state.wAxisY,
+			Axis::Z => state.wAxisZ,
+			Axis::RX => state.wAxisXRot,
+			Axis::RY => state.wAxisYRot,
+			Axis::RZ => state.wAxisZRot,
+			Axis::Slider => state.wSlider,
+			Axis::Dial => state.wDial,
+
+			Axis::Accelerator => state.wAccelerator,
+			Axis::Aileron => state.wAileron,
+			Axis::Brake => state.wBrake,
+			Axis::Clutch => state.wClutch,
+			Axis::Rudder => state.wRudder,
+			Axis::Steering => state.wSteering,
+			Axis::Throttle => state.wThrottle,
+			Axis::Wheel => state.wWheel,
+		}
+	}
+
+	/// Sets all POVs to their neutral (centered) position. This is valid for both continuous and
+	/// discrete POVs.
+	pub fn center_povs(&mut self) {
+		let state = &mut self.0;
+		state.bHats = u32::MAX;
+		state.bHatsEx1 = u32::MAX;
+		state.bHatsEx2 = u32::MAX;
+		state.bHatsEx3 = u32::MAX;
+	}
+
+	/// Returns the angle of a continuous POV in centidegrees, or `None` if it is centered. Returns
+	/// `None` (rather than `Some(None)`) if the index is beyond the POVs that vJoy supports.
+	pub fn cont_pov(&self, index: usize) -> Option<Option<u16>> {
+		let value = self.pov_field(index)?;
+		Some((value <= MAX_POV_CENTIDEGREES as u32).then_some(value as u16))
+	}
+
+	/// Sets the angle of a continuous POV in centidegrees, or centers it if `None` is given.
+	pub fn set_cont_pov(&mut self, index: usize, value: Option<u16>) -> Result<(), SetPovError> {
+		let raw = match value {
+			Some(angle) if angle > MAX_POV_CENTIDEGREES => return Err(SetPovError::Angle),
+			Some(angle) => angle as u32,
+			None => u32::MAX,
+		};
+
+		*self.pov_field_mut(index).ok_or(SetPovError::NoSuchPov)? = raw;
+		Ok(())
+	}
+
+	/// Returns the direction of a discrete POV. These share a field with continuous POV 0, so the two
+	/// kinds must not be mixed.
+	pub fn disc_pov(&self, index: usize) -> Option<PovDirection> {
+		(index < MAX_POVS).then(|| PovDirection::from_nibble((self.0.bHats >> (4 * index)) as u8))
+	}
+
+	pub fn set_disc_pov(&mut self, index: usize, dir: PovDirection) -> Result<(), SetPovError> {
+		if index >= MAX_POVS {
+			return Err(SetPovError::NoSuchPov);
+		}
+
+		let shift = 4 * index;
+		let hats = &mut self.0.bHats;
+		*hats = (*hats & !(0xF << shift)) | ((dir.to_nibble() as u32) << shift);
+		Ok(())
+	}
+
+	/// Returns the states of all buttons packed into a bit mask, where bit N is button N.
+	pub fn buttons_mask(&self) -> u128 {
+		(0..4).fold(0, |mask, w| mask | ((*self.button_word(w) as u32 as u128) << (32 * w)))
+	}
+
+	/// Sets the states of all buttons from a bit mask, with the same bit order as `buttons_mask`.
+	pub fn set_buttons_mask(&mut self, mask: u128) {
+		for w in 0..4 {
+			*self.button_word_mut(w) = (mask >> (32 * w)) as u32 as i32;
+		}
+	}
+
+	pub fn clear_buttons(&mut self) {
+		let state = &mut self.0;
+		state.lButtons = 0;
+		state.lButtonsEx1 = 0;
+		state.lButtonsEx2 = 0;
+		state.lButtonsEx3 = 0;
+	}
+
+	/// Changes which device this state is for, so that it may be applied to a different device.
+	pub fn retarget(&mut self, id: DeviceId) {
+		self.0.bDevice = id.into();
+	}
+
+	pub fn set_axis(&mut self, axis: Axis, value: i32) {
+		let state = &mut self.0;
+		match axis {
+			Axis::X => state.wAxisX = value,
+			Axis::Y => state.wAxisY = value,
+			Axis::Z => state.wAxisZ = value,
+			Axis::RX => state.wAxisXRot = value,
+			Axis::RY => state.wAxisYRot = value,
+			Axis::RZ => state.wAxisZRot = value,
+			Axis::Slider => state.wSlider = value,
+			Axis::Dial => state.wDial = value,
+
+			Axis::Accelerator => state.wAccelerator = value,
+			Axis::Aileron => state.wAileron = value,
+			Axis::Brake => state.wBrake = value,
+			Axis::Clutch => state.wClutch = value,
+			Axis::Rudder => state.wRudder = value,
+			Axis::Steering => state.wSteering = value,
+			Axis::Throttle => state.wThrottle = value,
+			Axis::Wheel => state.wWheel = value,
+		}
+	}
+
+	/// Determines which categories of inputs differ between two states.
+	pub(crate) fn diff(&self, other: &DeviceState) -> DirtyFlags {
+		DirtyFlags {
+			axes: Axis::all().any(|a| self.axis(a) != other.axis(a)),
+			buttons: (0..4).any(|w| self.button_word(w) != other.button_word(w)),
+			povs: (0..MAX_POVS).any(|i| self.pov_field(i) != other.pov_field(i)),
+		}
+	}
+
+	/// Copies the inputs which differ between `before` and `after` into this state, leaving all other
+	/// inputs as they are. Buttons are merged bit by bit, and axes and POV fields as whole values.
+	pub(crate) fn merge_changes(&mut self, before: &DeviceState, after: &DeviceState) {
+		for axis in Axis::all() {
+			if after.axis(axis) != before.axis(axis) {
+				self.set_axis(axis, after.axis(axis));
+			}
+		}
+
+		let changed = before.buttons_mask() ^ after.buttons_mask();
+		self.set_buttons_mask((self.buttons_mask() & !changed) | (after.buttons_mask() & changed));
+
+		for i in 0..MAX_POVS {
+			if after.pov_field(i) != before.pov_field(i) {
+				if let (Some(field), Some(value)) = (self.pov_field_mut(i), after.pov_field(i)) {
+					*field = value;
+				}
+			}
+		}
+	}
+
+	fn pov_field(&self, index: usize) -> Option<u32> {
+		let state = &self.0;
+		match index {
+			0 => Some(state.bHats),
+			1 => Some(state.bHatsEx1),
+			2 => Some(state.bHatsEx2),
+			3 => Some(state.bHatsEx3),
+			_ => None,
+		}
+	}
+
+	fn pov_field_mut(&mut self, index: usize) -> Option<&mut u32> {
+		let state = &mut self.0;
+		match index {
+			0 => Some(&mut state.bHats),
+			1 => Some(&mut state.bHatsEx1),
+			2 => Some(&mut state.bHatsEx2),
+			3 => Some(&mut state.bHatsEx3),
+			_ => None,
+		}
+	}
+
+	pub fn button(&self, index: usize) -> Option<bool> {
+		let (word, bit) = button_location(index)?;
+		Some((*self.button_word(word) & (1 << bit)) != 0)
+	}
+
+	fn button_word(&self, word: usize) -> &i32 {
+		let state = &self.0;
+		match word {
+			0 => &state.lButtons,
+			1 => &state.lButtonsEx1,
+			2 => &state.lButtonsEx2,
+			3 => &state.lButtonsEx3,
+			_ => unreachable!("invalid button word index {}", word),
+		}
+	}
+
+	fn button_word_mut(&mut self, word: usize) -> &mut i32 {
+		let state = &mut self.0;
+		match word {
+			0 => &mut state.lButtons,
+			1 => &mut state.lButtonsEx1,
+			2 => &mut state.lButtonsEx2,
+			3 => &mut state.lButtonsEx3,
+			_ => unreachable!("invalid button word index {}", word),
+		}
+	}
+
+	pub fn set_button(&mut self, index: usize, value: bool) -> Result<(), SetButtonError> {
+		let (word, bit) = button_location(index)
+			.ok_or(SetButtonError::NoSuchButton)?;
+
+		let word = self.button_word_mut(word);
+		let mask = 1 << bit;
+		*word = if value { *word | mask } else { *word & !mask };
+		Ok(())
+	}
+
+	/// Checks that this state only uses inputs supported by a device with the given capabilities.
+	/// Unsupported axes are accepted at 0 or at the center of vJoy's default range.
+	pub fn validate(&self, caps: &Capabilities) -> Result<(), ValidationError> {
+		let mut violations = Vec::new();
+
+		let center = axis_center(&STANDARD_AXIS_RANGE);
+		for axis in Axis::all() {
+			let value = self.axis(axis);
+			if !caps.has_axis(axis) && value != 0 && value != center {
+				violations.push(Violation::Axis(axis));
+			}
+		}
+
+		for index in caps.num_buttons()..MAX_BUTTONS {
+			if self.button(index) == Some(true) {
+				violations.push(Violation::Button(index));
+			}
+		}
+
+		// Discrete POVs are packed into the nibbles of the first POV field, so that field is only
+		// checked as a continuous POV if the device has no discrete POVs.
+		let mut first_cont = caps.num_cont_pov();
+		if caps.num_disc_pov() > 0 {
+			first_cont = first_cont.max(1);
+		}
+
+		for index in first_cont..MAX_POVS {
+			let value = self.pov_field(index).unwrap();
+			if value != 0 && value != u32::MAX {
+				violations.push(Violation::ContPov(index));
+			}
+		}
+
+		if caps.num_disc_pov() > 0 {
+			let packed = self.pov_field(0).unwrap();
+			for index in caps.num_disc_pov()..MAX_POVS {
+				let nibble = (packed >> (4 * index)) & 0xf;
+				if nibble != 0 && nibble != 0xf {
+					violations.push(Violation::DiscPov(index));
+				}
+			}
+		}
+
+		if violations.is_empty() {
+			Ok(())
+		} else {
+			Err(ValidationError(violations))
+		}
+	}
+}
+
+// The serialized form of a `DeviceState`, which omits the device ID.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize, serde::Serialize)]
+struct SerdeState {
+	axes: [i32; crate::AXIS_COUNT],
+	buttons: u128,
+	povs: [u32; MAX_POVS],
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for DeviceState {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		SerdeState {
+			axes: Axis::ALL.map(|a| self.axis(a)),
+			buttons: self.buttons_mask(),
+			povs: std::array::from_fn(|i| self.pov_field(i).unwrap_or(u32::MAX)),
+		}.serialize(serializer)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for DeviceState {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let serde = SerdeState::deserialize(deserializer)?;
+
+		// The device ID is left as 0 until the state is restored onto a device.
+		let mut state = DeviceState(unsafe { std::mem::zeroed() });
+		for (axis, value) in Axis::all().zip(serde.axes) {
+			state.set_axis(axis, value);
+		}
+
+		state.set_buttons_mask(serde.buttons);
+		for (i, value) in serde.povs.into_iter().enumerate() {
+			if let Some(field) = state.pov_field_mut(i) {
+				*field = value;
+			}
+		}
+
+		Ok(state)
+	}
+}
+
+/// `DirtyFlags` indicates which categories of inputs have been modified in a device's buffered state
+/// since it was last applied.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct DirtyFlags {
+	pub axes: bool,
+	pub buttons: bool,
+	pub povs: bool,
+}
+
+impl DirtyFlags {
+	pub const ALL: DirtyFlags = DirtyFlags { axes: true, buttons: true, povs: true };
+
+	pub fn any(&self) -> bool {
+		self.axes || self.buttons || self.povs
+	}
+}
+
+impl BitOr for DirtyFlags {
+	type Output = DirtyFlags;
+
+	fn bitor(self, rhs: DirtyFlags) -> DirtyFlags {
+		DirtyFlags {
+			axes: self.axes || rhs.axes,
+			buttons: self.buttons || rhs.buttons,
+			povs: self.povs || rhs.povs,
+		}
+	}
+}
+
+/// Maps a button index to the index of the button word it is stored in (`lButtons`, `lButtonsEx1`,
+/// etc.) and its bit within that word.
+fn button_location(index: usize) -> Option<(usize, usize)> {
+	(index < MAX_BUTTONS).then_some((index / 32, index % 32))
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Violation {
+	Axis(Axis),
+	Button(usize),
+	ContPov(usize),
+	DiscPov(usize),
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, thiserror::Error)]
+#[error("The device state uses {} input(s) which the device does not support.", .0.len())]
+pub struct ValidationError(Vec<Violation>);
+
+impl ValidationError {
+	pub fn violations(&self) -> &[Violation] {
+		&self.0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::{BTreeSet};
+
+	use super::*;
+
+	#[test]
+	fn button_locations_are_unique() {
+		let locations: BTreeSet<_> = (0..MAX_BUTTONS)
+			.map(|i| button_location(i).unwrap())
+			.collect();
+
+		assert_eq!(locations.len(), MAX_BUTTONS);
+		assert!(locations.iter().all(|&(word, bit)| word < MAX_BUTTONS / 32 && bit < 32));
+		assert_eq!(button_location(MAX_BUTTONS), None);
+	}
+
+	#[test]
+	fn unsupported_axes_may_be_centered() {
+		let caps = Capabilities {
+			num_buttons: 8,
+			num_cont_pov: 0,
+			num_disc_pov: 0,
+			axes: [(Axis::X, STANDARD_AXIS_RANGE)].into(),
+		};
+
+		let mut state = DeviceState::new(DeviceId::from_raw(1).unwrap());
+		state.set_axis(Axis::X, 1234);
+		state.set_axis(Axis::Y, axis_center(&STANDARD_AXIS_RANGE));
+		assert!(state.validate(&caps).is_ok());
+
+		state.set_axis(Axis::Z, 1);
+		state.set_axis(Axis::Slider, *STANDARD_AXIS_RANGE.end());
+		let error = state.validate(&caps).unwrap_err();
+		assert_eq!(error.violations(), [Violation::Axis(Axis::Z), Violation::Axis(Axis::Slider)]);
+	}
+}
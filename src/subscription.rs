@@ -0,0 +1,117 @@
+use std::ops::{Deref};
+use std::sync::{Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use crate::lock::{VJoyLock};
+
+/// The current subscriber to one of vJoy's callbacks. vJoy supports only a single callback of each
+/// kind, so each new subscription replaces the previous one.
+#[derive(Debug)]
+pub(crate) struct CallbackSlot<T> {
+	// The sender for the current subscription, along with its generation, which is used to tell
+	// whether a subscription is still the current one when it is dropped.
+	current: Mutex<Option<(u64, Sender<T>)>>,
+}
+
+impl<T> CallbackSlot<T> {
+	pub(crate) const fn new() -> CallbackSlot<T> {
+		CallbackSlot { current: Mutex::new(None) }
+	}
+
+	/// Sends a value to the current subscription, if any. This is called from vJoy's callbacks, so
+	/// it never panics.
+	pub(crate) fn send(&self, value: T) {
+		if let Ok(current) = self.current.lock() {
+			if let Some((_, sender)) = current.as_ref() {
+				sender.send(value).ok();
+			}
+		}
+	}
+}
+
+/// A `Subscription` receives the values sent to a `CallbackSlot` for as long as it is the slot's
+/// current subscription. When the current subscription is dropped, `unregister` is called to
+/// unregister the callback from vJoy.
+#[derive(Debug)]
+pub(crate) struct Subscription<T: 'static> {
+	receiver: Receiver<T>,
+	generation: u64,
+	slot: &'static CallbackSlot<T>,
+	unregister: fn(),
+	_lock: VJoyLock,
+}
+
+impl<T> Subscription<T> {
+	/// Makes a new subscription the current one for the slot. The caller is responsible for
+	/// registering the callback with vJoy afterwards.
+	pub(crate) fn new(slot: &'static CallbackSlot<T>, lock: VJoyLock, unregister: fn()) -> Subscription<T> {
+		let (sender, receiver) = mpsc::channel();
+
+		// Generations are never reused, so an old subscription can never be mistaken for the current
+		// one, even after subscriptions in between have been dropped.
+		let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+		*slot.current.lock().unwrap() = Some((generation, sender));
+
+		Subscription { receiver, generation, slot, unregister, _lock: lock }
+	}
+}
+
+impl<T> Deref for Subscription<T> {
+	type Target = Receiver<T>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.receiver
+	}
+}
+
+impl<T> Drop for Subscription<T> {
+	fn drop(&mut self) {
+		let is_current = {
+			let mut current = self.slot.current.lock().unwrap();
+			current.take_if(|(g, _)| *g == self.generation).is_some()
+		};
+
+		// The mutex is released first, since the callback may be waiting on it.
+		if is_current {
+			(self.unregister)();
+		}
+	}
+}
+
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::{AtomicUsize};
+
+	use super::*;
+
+	static SLOT: CallbackSlot<u32> = CallbackSlot::new();
+	static UNREGISTERED: AtomicUsize = AtomicUsize::new(0);
+
+	fn subscribe(lock: &VJoyLock) -> Subscription<u32> {
+		Subscription::new(&SLOT, lock.clone(), || { UNREGISTERED.fetch_add(1, Ordering::Relaxed); })
+	}
+
+	#[test]
+	fn stale_subscription_does_not_unregister_current() {
+		let _serial = crate::lock::serialize_test();
+		let lock = VJoyLock::new().unwrap();
+
+		let a = subscribe(&lock);
+		let b = subscribe(&lock);
+		drop(b);
+		assert_eq!(UNREGISTERED.load(Ordering::Relaxed), 1);
+
+		let c = subscribe(&lock);
+		drop(a);
+		assert_eq!(UNREGISTERED.load(Ordering::Relaxed), 1);
+
+		SLOT.send(7);
+		assert_eq!(c.try_recv(), Ok(7));
+
+		drop(c);
+		assert_eq!(UNREGISTERED.load(Ordering::Relaxed), 2);
+	}
+}
@@ -0,0 +1,42 @@
+use std::time::{Duration};
+
+use crate::state::{DeviceState};
+
+/// A `Timeline` is a sequence of device states, each with a timestamp relative to the start of the
+/// timeline. It may be played back onto a device with `OwnedDeviceSlot::play`.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct Timeline {
+	entries: Vec<(Duration, DeviceState)>,
+}
+
+impl Timeline {
+	pub fn new() -> Timeline {
+		Timeline::default()
+	}
+
+	/// The timestamp of the last state in the timeline, or zero if it is empty.
+	pub fn duration(&self) -> Duration {
+		self.entries.last().map_or(Duration::ZERO, |(at, _)| *at)
+	}
+
+	/// The timestamped states in the timeline, in order of their timestamps.
+	pub fn entries(&self) -> &[(Duration, DeviceState)] {
+		&self.entries
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	/// Adds a state to the timeline at the given timestamp. States with equal timestamps are kept in
+	/// the order that they were added.
+	pub fn push(&mut self, at: Duration, state: DeviceState) {
+		let index = self.entries.partition_point(|(t, _)| *t <= at);
+		self.entries.insert(index, (at, state));
+	}
+}
@@ -1,7 +1,40 @@
 use std::string::{FromUtf16Error};
 
+/// The maximum length, in UTF-16 code units, of a string read by `decode_utf16`. The strings that
+/// vJoy returns are stored in small fixed-size buffers, so this is never expected to be reached.
+const MAX_UTF16_LEN: usize = 256;
+
+/// Decodes a NUL-terminated UTF-16 string. A null pointer decodes to an empty string, and a string
+/// which is not terminated within `MAX_UTF16_LEN` code units is truncated to that length.
 pub unsafe fn decode_utf16(s: *const u16) -> Result<String, FromUtf16Error> {
-	let len = (0..).position(|i| s.offset(i).read() == 0).unwrap();
+	if s.is_null() {
+		return Ok(String::new());
+	}
+
+	let len = (0..MAX_UTF16_LEN).position(|i| s.add(i).read() == 0).unwrap_or(MAX_UTF16_LEN);
 	let slice = std::slice::from_raw_parts(s, len);
 	String::from_utf16(slice)
 }
+
+/// A minimal implementation of the 64-bit FNV-1a hash function.
+///
+/// Unlike the hashers in the standard library, its output is fully specified, so it is suitable for
+/// values which must be reproducible across runs, platforms, and compiler versions.
+pub struct Fnv1a(u64);
+
+impl Fnv1a {
+	pub fn new() -> Fnv1a {
+		Fnv1a(0xcbf2_9ce4_8422_2325)
+	}
+
+	pub fn write(&mut self, bytes: &[u8]) {
+		for &b in bytes {
+			self.0 ^= b as u64;
+			self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+		}
+	}
+
+	pub fn finish(&self) -> u64 {
+		self.0
+	}
+}
@@ -1,10 +1,12 @@
 use std::fmt::{Debug, Display};
 use std::num::{NonZeroU16};
+use std::str::{FromStr};
 
-#[derive(Clone, Copy, Eq, Hash, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Versions {
     pub(crate) driver_version: Result<Version, VersionError>,
     pub(crate) interface_version: Result<Version, VersionError>,
+    pub(crate) matched: bool,
 }
 
 impl Versions {
@@ -16,6 +18,25 @@ impl Versions {
         self.interface_version.map_err(Into::into)
     }
 
+    /// Returns whether the vJoy driver and interface library versions match, as reported by the
+    /// interface library.
+    pub fn matched(&self) -> bool {
+        self.matched
+    }
+
+    /// Checks that the vJoy driver and interface library versions match, as reported by the
+    /// interface library, returning both versions if they do not.
+    pub fn require_match(&self) -> Result<(), VersionMismatchError> {
+        if self.matched {
+            Ok(())
+        } else {
+            Err(VersionMismatchError {
+                driver_version: self.driver_version.ok(),
+                interface_version: self.interface_version.ok(),
+            })
+        }
+    }
+
     pub fn sdk_version(&self) -> Version {
         static_assertions::const_assert_eq!(vjoy_sys::VERSION_N as u16 as u32, vjoy_sys::VERSION_N);
         static_assertions::const_assert_ne!(vjoy_sys::VERSION_N as u16, 0);
@@ -26,10 +47,23 @@ impl Versions {
 }
 
 
-#[derive(Clone, Copy, Eq, Hash, PartialEq, PartialOrd)]
+/// A vJoy version number, packed into a `u16` as `0xMmp` (major, minor, and patch nibbles).
+///
+/// Versions are ordered by their raw value, which orders them by major, then minor, then patch.
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Version(NonZeroU16);
 
 impl Version {
+    /// Packs a major, minor, and patch version into a `Version`. Returns `None` if any part does not
+    /// fit in a nibble, or if all parts are zero.
+    pub fn from_parts(major: u8, minor: u8, patch: u8) -> Option<Self> {
+        if major > 0xf || minor > 0xf || patch > 0xf {
+            return None;
+        }
+
+        Version::from_raw((major as u16) << 8 | (minor as u16) << 4 | patch as u16)
+    }
+
     pub fn from_raw(raw: u16) -> Option<Self> {
         NonZeroU16::new(raw).map(Version)
     }
@@ -67,6 +101,30 @@ impl Display for Version {
     }
 }
 
+impl FromStr for Version {
+    type Err = ParseVersionError;
+
+    /// Parses a version in the `major.minor.patch` form used by its `Display` implementation.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('.')
+            .map(|p| p.parse::<u8>().map_err(|_| ParseVersionError::Format));
+
+        let (Some(major), Some(minor), Some(patch), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            return Err(ParseVersionError::Format);
+        };
+
+        Version::from_parts(major?, minor?, patch?)
+            .ok_or(ParseVersionError::OutOfRange)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Version {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
 #[error("Failed to get driver version number: {}", .0)]
 pub struct DriverVersionError(#[from] VersionError);
@@ -75,8 +133,81 @@ pub struct DriverVersionError(#[from] VersionError);
 #[error("Failed to get interface version number: {}", .0)]
 pub struct InterfaceVersionError(#[from] VersionError);
 
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
+pub enum ParseVersionError {
+    #[error("The version is not of the form `major.minor.patch`.")]
+    Format,
+
+    #[error("The version has a part greater than 15, or is 0.0.0.")]
+    OutOfRange,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
+#[error("The vJoy driver version ({}) does not match the interface version ({}).", display_version(.driver_version), display_version(.interface_version))]
+pub struct VersionMismatchError {
+    pub(crate) driver_version: Option<Version>,
+    pub(crate) interface_version: Option<Version>,
+}
+
+impl VersionMismatchError {
+    pub fn driver_version(&self) -> Option<Version> {
+        self.driver_version
+    }
+
+    pub fn interface_version(&self) -> Option<Version> {
+        self.interface_version
+    }
+}
+
+fn display_version(version: &Option<Version>) -> String {
+    version.map_or_else(|| String::from("unknown"), |v| v.to_string())
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, thiserror::Error)]
 pub enum VersionError {
+    #[error("The call to the vJoy interface to get version numbers failed.")]
+    CallFailed,
+
     #[error("The vJoy interface did not return a version number.")]
     Failed,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(major: u8, minor: u8, patch: u8) -> Version {
+        Version::from_parts(major, minor, patch).unwrap()
+    }
+
+    #[test]
+    fn versions_order_by_parts() {
+        assert!(v(2, 1, 9) < v(2, 1, 10));
+        assert!(v(2, 1, 15) < v(2, 2, 0));
+        assert!(v(2, 15, 15) < v(3, 0, 0));
+        assert!(v(0, 0, 1) < v(0, 1, 0));
+
+        let versions: Vec<_> = (1..=0xfff).filter_map(Version::from_raw).collect();
+        for pair in versions.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert_eq!(pair[0].cmp(&pair[1]), pair[0].parts().cmp(&pair[1].parts()));
+        }
+    }
+
+    #[test]
+    fn version_from_str_round_trips() {
+        for version in (1..=0xfff).filter_map(Version::from_raw) {
+            assert_eq!(version.to_string().parse(), Ok(version));
+        }
+
+        assert_eq!("2.1.9".parse(), Ok(v(2, 1, 9)));
+        assert_eq!("16.0.0".parse::<Version>(), Err(ParseVersionError::OutOfRange));
+        assert_eq!("2.16.0".parse::<Version>(), Err(ParseVersionError::OutOfRange));
+        assert_eq!("2.1.16".parse::<Version>(), Err(ParseVersionError::OutOfRange));
+        assert_eq!("0.0.0".parse::<Version>(), Err(ParseVersionError::OutOfRange));
+
+        for s in ["", "2", "2.1", "2.1.9.0", "2.1.x", "2..9", "256.0.0", "-1.0.0"] {
+            assert_eq!(s.parse::<Version>(), Err(ParseVersionError::Format), "{:?}", s);
+        }
+    }
+}